@@ -7,6 +7,7 @@ uniform vec4 _base_color;
 
 in vec2 _uv_coord;
 in float _tm;
+in vec4 _vert_color;
 
 // spritebatch
 in vec4 _sb_color;
@@ -19,7 +20,7 @@ float _time;
 @
 
 vec4 effect() {
-    return _base_color * texture2D(_tx_diffuse, _uv_coord);
+    return _base_color * _vert_color * texture2D(_tx_diffuse, _uv_coord);
 }
 
 @
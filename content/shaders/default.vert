@@ -8,6 +8,9 @@ layout (location = 3) in vec2  _ext_sb_position;
 layout (location = 4) in vec2  _ext_sb_scale;
 layout (location = 5) in float _ext_sb_rotation;
 layout (location = 6) in vec4  _ext_sb_color;
+layout (location = 13) in uvec4 _ext_sb_corners;
+
+layout (location = 7) in vec4 _ext_color;
 
 // basic
 uniform mat3 _screen;
@@ -19,6 +22,7 @@ uniform int _flip_uvs;
 out vec2 _uv_coord;
 out float _tm;
 out vec3 _normal;
+out vec4 _vert_color;
 
 // spritebatch
 mat3 _sb_model;
@@ -38,6 +42,10 @@ mat3 mat3_from_transform2d(float x, float y, float sx, float sy, float r) {
     return ret;
 }
 
+vec4 unpack_color(uint c) {
+    return vec4(c & 0xffu, (c >> 8) & 0xffu, (c >> 16) & 0xffu, c >> 24) / 255.0;
+}
+
 void ready_spritebatch() {
     // scale main uv coords by sb_uv
     //   automatically handles flip uvs
@@ -47,7 +55,10 @@ void ready_spritebatch() {
     _sb_uv.x = _uv_coord.x * uv_w + _ext_sb_uv.x;
     _sb_uv.y = _uv_coord.y * uv_h + _ext_sb_uv.y;
 
-    _sb_color = _ext_sb_color;
+    // corner colors blended by the mesh's own uvs, so flipping doesn't move them
+    vec4 top = mix(unpack_color(_ext_sb_corners.x), unpack_color(_ext_sb_corners.y), _ext_uv.x);
+    vec4 bottom = mix(unpack_color(_ext_sb_corners.w), unpack_color(_ext_sb_corners.z), _ext_uv.x);
+    _sb_color = _ext_sb_color * mix(top, bottom, _ext_uv.y);
     _sb_model = mat3_from_transform2d(_ext_sb_position.x,
                                       _ext_sb_position.y,
                                       _ext_sb_scale.x,
@@ -66,5 +77,6 @@ vec3 effect() {
 void main() {
     _uv_coord = _flip_uvs != 0 ? vec2(_ext_uv.x, 1 - _ext_uv.y) : _ext_uv;
     _tm = _time;
+    _vert_color = _ext_color;
     gl_Position = vec4(effect(), 1.0);
 }
@@ -0,0 +1,36 @@
+// position relative to the shape's center
+in vec2 _sdf_local;
+// half width, half height, corner radius, border width
+in vec4 _sdf_params;
+in vec4 _sdf_border_color;
+// offset x, offset y, blur
+in vec4 _sdf_shadow;
+in vec4 _sdf_shadow_color;
+
+float sdf_round_box(vec2 p, vec2 half_size, float r) {
+    vec2 q = abs(p) - half_size + r;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - r;
+}
+
+vec4 effect() {
+    float d = sdf_round_box(_sdf_local, _sdf_params.xy, _sdf_params.z);
+    // about one pixel on screen, at any scale
+    float aa = max(fwidth(d), 0.0001);
+
+    vec4 color = _vert_color;
+    float border = _sdf_params.w;
+    if (border > 0.0) {
+        float t = smoothstep(-border - aa * 0.5, -border + aa * 0.5, d);
+        color = mix(color, _sdf_border_color, t);
+    }
+    color.a *= 1.0 - smoothstep(-aa * 0.5, aa * 0.5, d);
+
+    float sd = sdf_round_box(_sdf_local - _sdf_shadow.xy, _sdf_params.xy, _sdf_params.z);
+    float blur = max(_sdf_shadow.z, aa);
+    float shadow = _sdf_shadow_color.a * (1.0 - smoothstep(-blur, blur, sd));
+
+    // shape over shadow
+    float a = color.a + shadow * (1.0 - color.a);
+    vec3 rgb = (color.rgb * color.a + _sdf_shadow_color.rgb * shadow * (1.0 - color.a)) / max(a, 0.0001);
+    return _base_color * vec4(rgb, a);
+}
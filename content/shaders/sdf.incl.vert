@@ -0,0 +1,20 @@
+layout (location = 8)  in vec2 _ext_sdf_local;
+layout (location = 9)  in vec4 _ext_sdf_params;
+layout (location = 10) in vec4 _ext_sdf_border_color;
+layout (location = 11) in vec4 _ext_sdf_shadow;
+layout (location = 12) in vec4 _ext_sdf_shadow_color;
+
+out vec2 _sdf_local;
+out vec4 _sdf_params;
+out vec4 _sdf_border_color;
+out vec4 _sdf_shadow;
+out vec4 _sdf_shadow_color;
+
+vec3 effect() {
+    _sdf_local = _ext_sdf_local;
+    _sdf_params = _ext_sdf_params;
+    _sdf_border_color = _ext_sdf_border_color;
+    _sdf_shadow = _ext_sdf_shadow;
+    _sdf_shadow_color = _ext_sdf_shadow_color;
+    return _screen * _view * _model * vec3(_ext_vertex, 1.0);
+}
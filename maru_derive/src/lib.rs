@@ -0,0 +1,179 @@
+//! # maru_derive
+//!
+//! derive macros for maru
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input,
+    spanned::Spanned,
+    Attribute,
+    Data,
+    DeriveInput,
+    Error,
+    Fields,
+    Lit,
+    Meta,
+};
+
+//
+
+/// Derives `maru::gfx::Vertex` and `maru::gfx::VertexAttributes`.
+///
+/// Every field is given an attribute location, counting up from 0,
+///   or from the last `#[location = N]`.
+/// Field types must implement `VertexAttributes`,
+///   structs deriving `Vertex` can be nested and will take up as many locations as they have fields,
+///   matrices take up one location per column.
+/// Integer fields are converted to floats unless marked `#[normalized]` or `#[integer]`.
+///
+/// Attributes:
+///   `#[location = N]` on a field sets its location,
+///     relative to the location the struct starts at,
+///     so nested structs keep their own numbering
+///   `#[normalized]` on a field normalizes integer types to floats
+///   `#[integer]` on a field passes integer types to the shader as integers
+///   `#[divisor = N]` on a field or the struct sets the instancing divisor
+#[proc_macro_derive(Vertex, attributes(location, normalized, integer, divisor))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match impl_vertex(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+//
+
+#[derive(Default)]
+struct FieldOptions {
+    location: Option<u32>,
+    normalized: bool,
+    integer: bool,
+    divisor: Option<u32>,
+}
+
+fn parse_u32(lit: &Lit) -> Result<u32, Error> {
+    match lit {
+        Lit::Int(int) => int.base10_parse(),
+        _ => Err(Error::new(lit.span(), "expected integer")),
+    }
+}
+
+fn parse_options(attrs: &[Attribute]) -> Result<FieldOptions, Error> {
+    let mut ret = FieldOptions::default();
+
+    for attr in attrs {
+        if attr.path.is_ident("location") {
+            match attr.parse_meta()? {
+                Meta::NameValue(nv) => ret.location = Some(parse_u32(&nv.lit)?),
+                meta => return Err(Error::new(meta.span(), "expected `#[location = N]`")),
+            }
+        } else if attr.path.is_ident("divisor") {
+            match attr.parse_meta()? {
+                Meta::NameValue(nv) => ret.divisor = Some(parse_u32(&nv.lit)?),
+                meta => return Err(Error::new(meta.span(), "expected `#[divisor = N]`")),
+            }
+        } else if attr.path.is_ident("normalized") {
+            match attr.parse_meta()? {
+                Meta::Path(_) => ret.normalized = true,
+                meta => return Err(Error::new(meta.span(), "expected `#[normalized]`")),
+            }
+        } else if attr.path.is_ident("integer") {
+            match attr.parse_meta()? {
+                Meta::Path(_) => ret.integer = true,
+                meta => return Err(Error::new(meta.span(), "expected `#[integer]`")),
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+fn impl_vertex(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new(input.span(), "`Vertex` can only be derived for structs with named fields")),
+        },
+        _ => return Err(Error::new(input.span(), "`Vertex` can only be derived for structs")),
+    };
+
+    let struct_options = parse_options(&input.attrs)?;
+    if struct_options.location.is_some() || struct_options.normalized || struct_options.integer {
+        return Err(Error::new(input.span(), "only `#[divisor = N]` can be used on the struct"));
+    }
+    let struct_divisor = struct_options.divisor.unwrap_or(0);
+
+    let mut enables = Vec::with_capacity(fields.len());
+    for field in fields {
+        let field_name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let options = parse_options(&field.attrs)?;
+        if options.normalized && options.integer {
+            return Err(Error::new(field.span(), "`#[normalized]` and `#[integer]` can't be used together"));
+        }
+
+        let set_location = options.location.map(| loc | {
+            quote! { at = location + #loc; }
+        });
+        let kind = if options.normalized {
+            quote! { ::maru::gfx::AttributeKind::Normalized }
+        } else if options.integer {
+            quote! { ::maru::gfx::AttributeKind::Integer }
+        } else {
+            quote! { base.kind }
+        };
+        let divisor = match options.divisor {
+            Some(div) => quote! { #div },
+            None => quote! { base.divisor },
+        };
+
+        enables.push(quote! {
+            #set_location
+            at = <#ty as ::maru::gfx::VertexAttributes>::enable_attributes(vao, at,
+                ::maru::gfx::VertexAttribute {
+                    kind: #kind,
+                    offset: base.offset + ::maru::memoffset::offset_of!(#name #ty_generics, #field_name),
+                    divisor: #divisor,
+                    .. base
+                })?;
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::maru::gfx::VertexAttributes for #name #ty_generics #where_clause {
+            #[allow(unused_assignments)]
+            fn enable_attributes(vao: &mut ::maru::gfx::VertexArray,
+                                 location: u32,
+                                 base: ::maru::gfx::VertexAttribute
+            ) -> ::std::result::Result<u32, ::std::string::String> {
+                let mut at = location;
+                #(#enables)*
+                ::std::result::Result::Ok(at)
+            }
+        }
+
+        impl #impl_generics ::maru::gfx::Vertex for #name #ty_generics #where_clause {
+            fn set_attributes(vao: &mut ::maru::gfx::VertexArray
+            ) -> ::std::result::Result<(), ::std::string::String> {
+                <Self as ::maru::gfx::VertexAttributes>::enable_attributes(vao, 0,
+                    ::maru::gfx::VertexAttribute {
+                        size: 0,
+                        ty: 0,
+                        kind: ::maru::gfx::AttributeKind::Float,
+                        stride: ::std::mem::size_of::<Self>(),
+                        offset: 0,
+                        divisor: #struct_divisor,
+                    })?;
+                ::std::result::Result::Ok(())
+            }
+        }
+    })
+}
@@ -34,6 +34,11 @@ impl CoordinateStack {
         self.stk.is_empty()
     }
 
+    /// All transforms on the stack composed together.
+    pub fn composed(&self) -> &glm::Mat3 {
+        &self.composed
+    }
+
     pub fn clear(&mut self) -> &glm::Mat3 {
         self.stk.clear();
         self.composed = glm::Mat3::identity();
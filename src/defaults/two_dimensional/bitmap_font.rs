@@ -1,4 +1,9 @@
-use image::RgbaImage;
+use std::collections::HashMap;
+
+use image::{
+    RgbaImage,
+    Rgba,
+};
 use nalgebra_glm as glm;
 
 use crate::{
@@ -13,8 +18,25 @@ use super::images;
 
 //
 
-// TODO use bdf_font
-//        lookup table
+/// Where a character is in the font's texture, and how to place it.
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph {
+    pub region: TextureRegion,
+    pub uv: UvRegion,
+    /// From the pen position to the top left of the glyph, y down.
+    /// The pen sits at the top of the line.
+    pub offset: glm::Vec2,
+    /// How far the pen moves after this glyph.
+    pub advance: f32,
+}
+
+impl Glyph {
+    pub fn is_empty(&self) -> bool {
+        self.region.width() <= 0 || self.region.height() <= 0
+    }
+}
+
+// TODO lookup table
 //        glyph strings
 //          for sb.print
 //      include medium sized font and large font
@@ -26,8 +48,9 @@ use super::images;
 //      make font with default alphabet
 pub struct BitmapFont {
     texture: Texture,
-    regions: Vec<TextureRegion>,
-    uv_regions: Vec<UvRegion>,
+    glyphs: HashMap<char, Glyph>,
+    default_glyph: Glyph,
+    line_height: f32,
 }
 
 impl BitmapFont {
@@ -50,28 +73,27 @@ impl BitmapFont {
                   .map(| (x1, x2) | TextureRegion::new(x1 as i32 + 1, 0, x2 as i32, i_height))
                   .zip(alphabet.chars());
 
-        let mut regions = Vec::with_capacity(256);
-        // set to 0,0,1,1 so if char is not found, uv is base_color
-        regions.resize(256, TextureRegion::new(0, 0, 1, 1));
-
-        for (region, ch) in pairs {
-            regions[ch as usize] = region;
-        }
-
         let mut texture = Texture::new(image);
         texture.set_wrap(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE);
         texture.set_filter(gl::NEAREST, gl::NEAREST);
 
         let tx_point = glm::vec2(texture.width(), texture.height());
+        let glyph = | region: TextureRegion | Glyph {
+            region,
+            uv: region.normalized(tx_point),
+            offset: glm::vec2(0., 0.),
+            advance: (region.width() + 1) as f32,
+        };
 
-        let uv_regions = regions.iter()
-                                .map(| region | region.normalized(tx_point))
-                                .collect();
+        let glyphs = pairs.map(| (region, ch) | (ch, glyph(region)))
+                          .collect();
 
         Self {
             texture,
-            regions,
-            uv_regions,
+            glyphs,
+            // if char is not found, draws a line of base_color as tall as the font
+            default_glyph: glyph(TextureRegion::new(0, 0, 1, i_height)),
+            line_height: i_height as f32,
         }
     }
 
@@ -84,15 +106,408 @@ impl BitmapFont {
           1234567890[](){}=+-/^$@#*~%_<>\"'?!|\\&`.,:;")
     }
 
+    /// Loads a font in the BDF format, packing its glyphs into one texture.
+    /// Characters the font does not have are drawn as its `DEFAULT_CHAR`, or left blank.
+    pub fn from_bdf(text: &str) -> Result<Self, String> {
+        let bdf = parse_bdf(text)?;
+
+        // shelf packing, tallest glyphs first
+        const PADDING: u32 = 1;
+
+        let mut order: Vec<usize> = (0..bdf.glyphs.len()).collect();
+        order.sort_by(| &a, &b | bdf.glyphs[b].height.cmp(&bdf.glyphs[a].height));
+
+        let area: u32 = bdf.glyphs.iter()
+            .map(| g | (g.width + PADDING) * (g.height + PADDING))
+            .sum();
+        let widest = bdf.glyphs.iter().map(| g | g.width + 2 * PADDING).max().unwrap_or(1);
+        let atlas_w = ((area as f32).sqrt().ceil() as u32).max(widest).next_power_of_two();
+
+        let mut positions = vec![(0, 0); bdf.glyphs.len()];
+        let (mut x, mut y, mut shelf_h) = (PADDING, PADDING, 0);
+        for &i in order.iter() {
+            let g = &bdf.glyphs[i];
+            if x + g.width + PADDING > atlas_w {
+                x = PADDING;
+                y += shelf_h + PADDING;
+                shelf_h = 0;
+            }
+            positions[i] = (x, y);
+            x += g.width + PADDING;
+            shelf_h = shelf_h.max(g.height);
+        }
+        let atlas_h = (y + shelf_h + PADDING).next_power_of_two();
+
+        let mut image = RgbaImage::from_pixel(atlas_w, atlas_h, Rgba::from([255, 255, 255, 0]));
+        for (g, &(gx, gy)) in bdf.glyphs.iter().zip(positions.iter()) {
+            for row in 0..g.height {
+                for col in 0..g.width {
+                    if g.pixel(col, row) {
+                        image.put_pixel(gx + col, gy + row, Rgba::from([255, 255, 255, 255]));
+                    }
+                }
+            }
+        }
+
+        let mut texture = Texture::new(&image);
+        texture.set_wrap(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE);
+        texture.set_filter(gl::NEAREST, gl::NEAREST);
+
+        let tx_point = glm::vec2(texture.width(), texture.height());
+        let glyph = | g: &BdfGlyph, (gx, gy): (u32, u32) | {
+            let region = TextureRegion::new(gx as i32,
+                                            gy as i32,
+                                            (gx + g.width) as i32,
+                                            (gy + g.height) as i32);
+            // bdf boxes are y up from the baseline
+            Glyph {
+                region,
+                uv: region.normalized(tx_point),
+                offset: glm::vec2(g.x_offset as f32,
+                                  (bdf.ascent - g.y_offset - g.height as i32) as f32),
+                advance: g.advance as f32,
+            }
+        };
+
+        let glyphs: HashMap<char, Glyph> = bdf.glyphs.iter()
+            .zip(positions.iter())
+            .map(| (g, &at) | (g.ch, glyph(g, at)))
+            .collect();
+
+        let blank = Glyph {
+            region: TextureRegion::new(0, 0, 0, 0),
+            uv: UvRegion::new(0., 0., 0., 0.),
+            offset: glm::vec2(0., 0.),
+            advance: bdf.default_advance as f32,
+        };
+        let default_glyph = bdf.default_char
+                               .and_then(| ch | glyphs.get(&ch))
+                               .copied()
+                               .unwrap_or(blank);
+
+        Ok(Self {
+            texture,
+            glyphs,
+            default_glyph,
+            line_height: (bdf.ascent + bdf.descent) as f32,
+        })
+    }
+
     pub fn texture(&self) -> &Texture {
         &self.texture
     }
 
+    pub fn glyph(&self, ch: char) -> &Glyph {
+        self.glyphs.get(&ch).unwrap_or(&self.default_glyph)
+    }
+
     pub fn region(&self, ch: char) -> TextureRegion {
-        self.regions[ch as usize]
+        self.glyph(ch).region
     }
 
     pub fn uv_region(&self, ch: char) -> UvRegion {
-        self.uv_regions[ch as usize]
+        self.glyph(ch).uv
+    }
+
+    /// Distance between the tops of two lines.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+}
+
+//
+
+#[derive(Debug)]
+struct BdfGlyph {
+    ch: char,
+    width: u32,
+    height: u32,
+    x_offset: i32,
+    y_offset: i32,
+    advance: i32,
+    /// Rows of bits, most significant bit first, each padded to a whole byte.
+    bitmap: Vec<u8>,
+}
+
+impl BdfGlyph {
+    fn pixel(&self, x: u32, y: u32) -> bool {
+        let row_bytes = row_bytes(self.width as usize);
+        let byte = self.bitmap[y as usize * row_bytes + x as usize / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+}
+
+#[derive(Debug)]
+struct Bdf {
+    glyphs: Vec<BdfGlyph>,
+    ascent: i32,
+    descent: i32,
+    default_char: Option<char>,
+    default_advance: i32,
+}
+
+/// Bytes in one bitmap row of a glyph `width` pixels wide.
+fn row_bytes(width: usize) -> usize {
+    width.div_ceil(8)
+}
+
+fn parse_bdf(text: &str) -> Result<Bdf, String> {
+    let mut lines = text.lines().enumerate().map(| (i, line) | (i + 1, line.trim()));
+
+    let mut font_box: Option<[i32; 4]> = None;
+    let mut ascent = None;
+    let mut descent = None;
+    let mut default_char = None;
+    let mut glyphs = Vec::new();
+    let mut started = false;
+
+    while let Some((num, line)) = lines.next() {
+        let err = | msg: &str | format!("bdf line {}: {}", num, msg);
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = words.collect();
+
+        match keyword {
+            "STARTFONT" => started = true,
+            _ if !started => return Err(err("expected STARTFONT")),
+            "FONTBOUNDINGBOX" => font_box = Some(parse_ints(&values).map_err(| e | err(&e))?),
+            "FONT_ASCENT" => ascent = Some(parse_int(&values).map_err(| e | err(&e))?),
+            "FONT_DESCENT" => descent = Some(parse_int(&values).map_err(| e | err(&e))?),
+            "DEFAULT_CHAR" => {
+                default_char = std::char::from_u32(parse_int(&values).map_err(| e | err(&e))? as u32);
+            }
+            "STARTCHAR" => {
+                if let Some(glyph) = parse_bdf_char(&mut lines, font_box)? {
+                    glyphs.push(glyph);
+                }
+            }
+            "ENDFONT" => break,
+            _ => {}
+        }
+    }
+
+    if !started {
+        return Err("bdf: expected STARTFONT".to_string());
+    }
+    let font_box = font_box.ok_or_else(|| "bdf: missing FONTBOUNDINGBOX".to_string())?;
+
+    Ok(Bdf {
+        glyphs,
+        ascent: ascent.unwrap_or(font_box[1] + font_box[3]),
+        descent: descent.unwrap_or(-font_box[3]),
+        default_char,
+        default_advance: font_box[0],
+    })
+}
+
+/// Reads up to `ENDCHAR`.
+/// Returns `None` for glyphs without a character code.
+fn parse_bdf_char<'a, I>(lines: &mut I, font_box: Option<[i32; 4]>) -> Result<Option<BdfGlyph>, String>
+where
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    let mut ch = None;
+    let mut bbx = font_box;
+    let mut advance = None;
+    let mut bitmap = Vec::new();
+
+    while let Some((num, line)) = lines.next() {
+        let err = | msg: &str | format!("bdf line {}: {}", num, msg);
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = words.collect();
+
+        match keyword {
+            // -1 is a glyph with no standard encoding
+            "ENCODING" => {
+                let code = parse_int(&values).map_err(| e | err(&e))?;
+                ch = if code >= 0 { std::char::from_u32(code as u32) } else { None };
+            }
+            "DWIDTH" => advance = Some(parse_int(&values).map_err(| e | err(&e))?),
+            "BBX" => bbx = Some(parse_ints(&values).map_err(| e | err(&e))?),
+            "BITMAP" => {
+                let [w, h, _, _] = bbx.ok_or_else(|| err("BITMAP before BBX"))?;
+                let row_bytes = row_bytes(w.max(0) as usize);
+                for _ in 0..h.max(0) {
+                    let (num, row) = lines.next().ok_or_else(|| err("unexpected end of bitmap"))?;
+                    for i in 0..row_bytes {
+                        let byte = row.get((i * 2)..(i * 2 + 2))
+                                      .and_then(| hex | u8::from_str_radix(hex, 16).ok())
+                                      .ok_or_else(|| format!("bdf line {}: invalid bitmap row", num))?;
+                        bitmap.push(byte);
+                    }
+                }
+            }
+            "ENDCHAR" => {
+                let [w, h, x_offset, y_offset] = bbx.ok_or_else(|| err("missing BBX"))?;
+                if bitmap.len() < row_bytes(w.max(0) as usize) * h.max(0) as usize {
+                    return Err(err("missing BITMAP"));
+                }
+                return Ok(ch.map(| ch | BdfGlyph {
+                    ch,
+                    width: w.max(0) as u32,
+                    height: h.max(0) as u32,
+                    x_offset,
+                    y_offset,
+                    advance: advance.unwrap_or(w),
+                    bitmap,
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    Err("bdf: missing ENDCHAR".to_string())
+}
+
+fn parse_int(values: &[&str]) -> Result<i32, String> {
+    values.first()
+          .and_then(| v | v.parse().ok())
+          .ok_or_else(|| format!("expected a number, found {:?}", values.first()))
+}
+
+fn parse_ints(values: &[&str]) -> Result<[i32; 4], String> {
+    let mut ret = [0; 4];
+    for (i, r) in ret.iter_mut().enumerate() {
+        *r = values.get(i)
+                   .and_then(| v | v.parse().ok())
+                   .ok_or_else(|| format!("expected 4 numbers, found {:?}", values))?;
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font(chars: &str) -> String {
+        format!("STARTFONT 2.1\n\
+                 FONTBOUNDINGBOX 9 8 0 -2\n\
+                 FONT_ASCENT 6\n\
+                 FONT_DESCENT 2\n\
+                 DEFAULT_CHAR 63\n\
+                 {}\
+                 ENDFONT\n", chars)
+    }
+
+    const CHAR_A: &str = "STARTCHAR A\n\
+                          ENCODING 65\n\
+                          DWIDTH 4 0\n\
+                          BBX 3 2 1 -1\n\
+                          BITMAP\n\
+                          A0\n\
+                          40\n\
+                          ENDCHAR\n";
+
+    #[test]
+    fn parses_header_and_glyph() {
+        let bdf = parse_bdf(&font(CHAR_A)).unwrap();
+        assert_eq!(bdf.ascent, 6);
+        assert_eq!(bdf.descent, 2);
+        assert_eq!(bdf.default_char, Some('?'));
+        assert_eq!(bdf.default_advance, 9);
+        assert_eq!(bdf.glyphs.len(), 1);
+
+        let g = &bdf.glyphs[0];
+        assert_eq!(g.ch, 'A');
+        assert_eq!((g.width, g.height), (3, 2));
+        assert_eq!((g.x_offset, g.y_offset), (1, -1));
+        assert_eq!(g.advance, 4);
+        assert_eq!(g.bitmap, vec![0xa0, 0x40]);
+    }
+
+    #[test]
+    fn reads_pixels_msb_first() {
+        let bdf = parse_bdf(&font(CHAR_A)).unwrap();
+        let g = &bdf.glyphs[0];
+        let rows: Vec<Vec<bool>> = (0..2).map(| y | (0..3).map(| x | g.pixel(x, y)).collect())
+                                         .collect();
+        assert_eq!(rows, vec![vec![true, false, true],
+                              vec![false, true, false]]);
+    }
+
+    #[test]
+    fn wide_rows_span_bytes() {
+        let wide = "STARTCHAR W\n\
+                    ENCODING 87\n\
+                    BBX 9 1 0 0\n\
+                    BITMAP\n\
+                    8080\n\
+                    ENDCHAR\n";
+        let bdf = parse_bdf(&font(wide)).unwrap();
+        let g = &bdf.glyphs[0];
+        assert_eq!(g.bitmap, vec![0x80, 0x80]);
+        assert!(g.pixel(0, 0));
+        assert!(!g.pixel(7, 0));
+        assert!(g.pixel(8, 0));
+        // no DWIDTH, advance is the box width
+        assert_eq!(g.advance, 9);
+    }
+
+    #[test]
+    fn glyph_defaults_to_font_box() {
+        let no_bbx = "STARTCHAR B\n\
+                      ENCODING 66\n\
+                      BITMAP\n\
+                      FF80\n0000\n0000\n0000\n0000\n0000\n0000\n0000\n\
+                      ENDCHAR\n";
+        let bdf = parse_bdf(&font(no_bbx)).unwrap();
+        let g = &bdf.glyphs[0];
+        assert_eq!((g.width, g.height), (9, 8));
+        assert_eq!((g.x_offset, g.y_offset), (0, -2));
+    }
+
+    #[test]
+    fn skips_unencoded_glyphs() {
+        let unencoded = "STARTCHAR none\n\
+                         ENCODING -1\n\
+                         BBX 1 1 0 0\n\
+                         BITMAP\n\
+                         80\n\
+                         ENDCHAR\n";
+        let bdf = parse_bdf(&font(&format!("{}{}", unencoded, CHAR_A))).unwrap();
+        assert_eq!(bdf.glyphs.len(), 1);
+        assert_eq!(bdf.glyphs[0].ch, 'A');
+    }
+
+    #[test]
+    fn ascent_and_descent_default_to_font_box() {
+        let text = "STARTFONT 2.1\n\
+                    FONTBOUNDINGBOX 5 10 0 -3\n\
+                    ENDFONT\n";
+        let bdf = parse_bdf(text).unwrap();
+        assert_eq!(bdf.ascent, 7);
+        assert_eq!(bdf.descent, 3);
+        assert_eq!(bdf.default_char, None);
+        assert!(bdf.glyphs.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_bdf("FONTBOUNDINGBOX 1 1 0 0\n").unwrap_err().contains("line 1"));
+        assert!(parse_bdf("").is_err());
+        assert!(parse_bdf("STARTFONT 2.1\nENDFONT\n").unwrap_err().contains("FONTBOUNDINGBOX"));
+        assert!(parse_bdf("STARTFONT 2.1\nFONT_ASCENT x\n").unwrap_err().contains("line 2"));
+
+        let bad_row = CHAR_A.replace("A0", "zz");
+        assert!(parse_bdf(&font(&bad_row)).unwrap_err().contains("invalid bitmap row"));
+
+        let short = CHAR_A.replace("40\n", "");
+        assert!(parse_bdf(&font(&short)).is_err());
+
+        let unterminated = CHAR_A.replace("ENDCHAR\n", "");
+        assert!(parse_bdf(&font(&unterminated)).unwrap_err().contains("ENDCHAR"));
+
+        let no_box = "STARTFONT 2.1\n\
+                      STARTCHAR A\n\
+                      BITMAP\n\
+                      ENDCHAR\n";
+        assert!(parse_bdf(no_box).unwrap_err().contains("BITMAP before BBX"));
     }
 }
@@ -1,3 +1,5 @@
+use std::thread;
+
 use image::{
     RgbaImage,
     Rgba,
@@ -10,6 +12,14 @@ use crate::{
     },
     gfx::{
         BoundInstancer,
+        CompareFunc,
+        FrameStats,
+        MeshId,
+        RenderState,
+        RenderStateCache,
+        ScissorRect,
+        StencilOp,
+        StencilState,
         TextureData,
         Texture,
         UvRegion,
@@ -18,17 +28,27 @@ use crate::{
         ext::*,
         Color,
         Transform2d,
+        AABB,
     },
 };
 
 use super::{
     SbSprite,
+    SpriteMeshes,
     Vertex2d,
     Spritebatch,
     Locations,
     BitmapFont,
+    SdfStyle,
     ShapeDrawer,
+    StrokeStyle,
+    LineCap,
     Program2d,
+    Path,
+    FillRule,
+    Svg,
+    GradientTexture,
+    init_vertex_color,
 };
 
 #[doc(no_inline)]
@@ -51,6 +71,8 @@ struct CanvasManager {
 pub struct DrawDefaults {
     pub program: Program2d,
     pub spritebatch_program: Program2d,
+    pub shape_program: Program2d,
+    pub sdf_program: Program2d,
     pub white_texture: Texture,
 }
 
@@ -58,12 +80,17 @@ impl DrawDefaults {
     pub fn new() -> Self {
         let program = Program2d::default_program();
         let spritebatch_program = Program2d::default_spritebatch_program();
+        let shape_program = Program2d::default_shape_program();
+        let sdf_program = Program2d::default_sdf_program();
+        init_vertex_color();
         let white = RgbaImage::from_pixel(1, 1, Rgba::from([255, 255, 255, 255]));
         let white_texture = Texture::new(&white);
 
         Self {
             program,
             spritebatch_program,
+            shape_program,
+            sdf_program,
             white_texture,
         }
     }
@@ -78,6 +105,7 @@ impl DrawDefaults {
 
 pub struct Drawer2d {
     coord_stack: CoordinateStack,
+    state_cache: RenderStateCache,
     sprites: Spritebatch,
     shapes: ShapeDrawer,
 }
@@ -86,45 +114,83 @@ impl Drawer2d {
     pub fn new() -> Self {
         let coord_stack = CoordinateStack::with_capacity(10);
         let sprites = Spritebatch::new(500);
-        let shapes = ShapeDrawer::new(50);
+        let shapes = ShapeDrawer::new();
 
         Self {
             coord_stack,
+            state_cache: RenderStateCache::new(),
             sprites,
             shapes,
         }
     }
 
+    /// Call this if GL state was changed outside of the drawer.
+    pub fn invalidate_render_state(&mut self) {
+        self.state_cache.invalidate();
+    }
+
     pub fn bind_spritebatch<'a>(&'a mut self,
                                 prog: &'a Program2d,
                                 texture: &'a Texture,
                                 canvas_dimensions: (u32, u32),
                                 centered_quad: bool,
     ) -> BoundSpritebatch<'a> {
+        let meshes = self.sprites.meshes();
         BoundSpritebatch {
-            base: BoundDrawer2d::new(&mut self.coord_stack, prog, texture, canvas_dimensions),
+            base: BoundDrawer2d::new(&mut self.coord_stack,
+                                     &mut self.state_cache,
+                                     prog,
+                                     texture,
+                                     canvas_dimensions),
             sb: self.sprites.bind(centered_quad),
+            meshes,
+            quad: if centered_quad { meshes.quad_centered } else { meshes.quad },
             sprite_color: Color::white(),
         }
     }
 
+    /// Shapes are batched and drawn when state changes or the drawer is dropped.
+    /// `prog` should read vertex colors, like `DrawDefaults::program`.
     pub fn bind_shape_drawer<'a>(&'a mut self,
                                  prog: &'a Program2d,
                                  texture: &'a Texture,
                                  canvas_dimensions: (u32, u32)
     ) -> BoundShapeDrawer<'a> {
+        self.shapes.set_scale(1.);
+        self.shapes.set_uv_region(UvRegion::new(0., 0., 1., 1.));
         BoundShapeDrawer {
-            base: BoundDrawer2d::new(&mut self.coord_stack, prog, texture, canvas_dimensions),
-            drawer: &self.shapes,
+            base: BoundDrawer2d::new(&mut self.coord_stack,
+                                     &mut self.state_cache,
+                                     prog,
+                                     texture,
+                                     canvas_dimensions),
+            drawer: &mut self.shapes,
+            sdf_prog: None,
         }
     }
 }
 
 //
 
+/// Which side of a mask is drawn to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MaskMode {
+    Inside,
+    Outside,
+}
+
+/// Max number of nested masks, limited by an 8 bit stencil buffer.
+pub const MAX_MASK_DEPTH: u8 = 255;
+
 // canvas manager goes here
 pub struct BoundDrawer2d<'a> {
     coord_stack: &'a mut CoordinateStack,
+    state_cache: &'a mut RenderStateCache,
+    render_state: RenderState,
+    clip_stack: Vec<AABB<f32>>,
+    mask_depth: u8,
+    mask_mode: MaskMode,
+    mask_writing: bool,
     prog: &'a Program2d,
     texture: &'a Texture,
     canvas_width: u32,
@@ -132,13 +198,23 @@ pub struct BoundDrawer2d<'a> {
 }
 
 impl<'a> BoundDrawer2d<'a> {
+    /// Starts with the default `RenderState`.
     pub fn new(coord_stack: &'a mut CoordinateStack,
+               state_cache: &'a mut RenderStateCache,
                prog: &'a Program2d,
                texture: &'a Texture,
                canvas_dimensions: (u32, u32)
     ) -> Self {
+        let render_state = RenderState::default();
+        state_cache.apply(&render_state);
         let mut ret = Self {
             coord_stack,
+            state_cache,
+            render_state,
+            clip_stack: Vec::new(),
+            mask_depth: 0,
+            mask_mode: MaskMode::Inside,
+            mask_writing: false,
             prog,
             texture,
             canvas_width: canvas_dimensions.0,
@@ -170,6 +246,21 @@ impl<'a> BoundDrawer2d<'a> {
         self.prog.locs.diffuse().set(&TextureData::diffuse(self.texture));
     }
 
+    /// Binds another program with the current screen, view and texture,
+    ///   without changing this drawer's program or coordinate stack.
+    /// Bind `self.prog` again with `rebind_program()` when done.
+    fn bind_secondary_program(&self, prog: &Program2d) {
+        prog.prog.bind();
+        prog.locs.reset();
+        prog.locs.screen().set(&ortho_screen(glm::vec2(self.canvas_width, self.canvas_height)));
+        prog.locs.view().set(self.coord_stack.composed());
+        prog.locs.diffuse().set(&TextureData::diffuse(self.texture));
+    }
+
+    fn rebind_program(&self) {
+        self.prog.prog.bind();
+    }
+
     pub fn push_coord(&mut self, t: CoordinateTransform) {
         self.prog.locs.view().set(self.coord_stack.push(t));
     }
@@ -183,6 +274,226 @@ impl<'a> BoundDrawer2d<'a> {
     pub fn locations(&mut self) -> &Locations {
         &self.prog.locs
     }
+
+    /// How many pixels one unit covers with the current coordinate transforms.
+    /// For non uniform scales, this is the larger of the two.
+    pub fn coordinate_scale(&self) -> f32 {
+        let m = self.coord_stack.composed();
+        let x = glm::vec2(m[(0, 0)], m[(1, 0)]).norm();
+        let y = glm::vec2(m[(0, 1)], m[(1, 1)]).norm();
+        x.max(y)
+    }
+
+    /// note: if there is a clip rect, it overrides the scissor of `state`
+    ///       if there is a mask, it overrides the stencil of `state`
+    pub fn set_render_state(&mut self, state: RenderState) {
+        self.render_state = state;
+        self.apply_render_state();
+    }
+
+    /// The state last set, without clip or mask overrides.
+    pub fn render_state(&self) -> &RenderState {
+        &self.render_state
+    }
+
+    fn apply_render_state(&mut self) {
+        let mut state = self.render_state;
+        if let Some(clip) = self.clip_stack.last() {
+            state.scissor = Some(clip_to_scissor(clip, self.canvas_height));
+        }
+        if let Some(stencil) = mask_stencil(self.mask_depth, self.mask_mode, self.mask_writing) {
+            state.stencil = Some(stencil);
+        }
+        if self.mask_writing {
+            state.color_mask = [false; 4];
+        }
+        self.state_cache.apply(&state);
+    }
+
+    /// Clips drawing to a rectangle in canvas coordinates, with the origin at the top left.
+    /// Nested clips are intersected with the clips below them.
+    /// If `transformed` is true, the rectangle is first transformed by the coordinate stack,
+    ///   rotated rectangles are clipped to their bounding box.
+    pub fn push_clip(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, transformed: bool) {
+        let transform = if transformed {
+            Some(self.coord_stack.composed())
+        } else {
+            None
+        };
+        let rect = clip_rect(AABB::new(x1, y1, x2, y2), transform, self.clip_stack.last());
+        self.clip_stack.push(rect);
+        self.apply_clip();
+    }
+
+    pub fn pop_clip(&mut self) {
+        if self.clip_stack.pop().is_some() {
+            self.apply_clip();
+        }
+    }
+
+    fn apply_clip(&mut self) {
+        self.apply_render_state();
+    }
+
+    /// Starts drawing a new mask, nested inside the current one.
+    /// Until `end_mask()`, anything drawn is only written to the stencil buffer.
+    /// note: the mask is the geometry drawn, transparent pixels of textures are still included
+    ///       the stencil buffer is cleared when starting the outermost mask
+    pub fn begin_mask(&mut self) {
+        assert!(!self.mask_writing, "mask already being drawn");
+        assert!(self.mask_depth < MAX_MASK_DEPTH, "mask stack overflow");
+
+        if self.mask_depth == 0 {
+            // clear the whole stencil buffer, ignoring clip rects
+            self.state_cache.apply(&self.render_state.with_scissor(None).with_stencil(None));
+            unsafe {
+                gl::ClearStencil(0);
+                gl::Clear(gl::STENCIL_BUFFER_BIT);
+            }
+        }
+
+        self.mask_writing = true;
+        self.apply_render_state();
+    }
+
+    /// Finishes the mask being drawn, further drawing is masked by it.
+    pub fn end_mask(&mut self, mode: MaskMode) {
+        assert!(self.mask_writing, "no mask being drawn");
+        self.mask_writing = false;
+        self.mask_depth += 1;
+        self.mask_mode = mode;
+        self.apply_render_state();
+    }
+
+    /// Changes which side of the current mask is drawn to.
+    pub fn set_mask_mode(&mut self, mode: MaskMode) {
+        self.mask_mode = mode;
+        self.apply_render_state();
+    }
+
+    pub fn mask_mode(&self) -> MaskMode {
+        self.mask_mode
+    }
+
+    /// Number of masks currently applied.
+    pub fn mask_depth(&self) -> u8 {
+        self.mask_depth
+    }
+
+    /// Sets up state to remove the current mask from the stencil buffer.
+    /// Returns false if there is no mask to pop.
+    /// Anything drawn until `end_pop_mask()` should cover the whole canvas, in canvas coordinates.
+    pub(crate) fn begin_pop_mask(&mut self) -> bool {
+        assert!(!self.mask_writing, "mask still being drawn");
+        if self.mask_depth == 0 {
+            return false;
+        }
+
+        // ignoring clip rects
+        let state = RenderState {
+            scissor: None,
+            stencil: Some(pop_mask_stencil(self.mask_depth)),
+            color_mask: [false; 4],
+            .. self.render_state
+        };
+        self.state_cache.apply(&state);
+        self.prog.locs.view().set(&glm::Mat3::identity());
+        true
+    }
+
+    pub(crate) fn end_pop_mask(&mut self) {
+        self.prog.locs.view().set(self.coord_stack.composed());
+        self.mask_depth -= 1;
+        self.mask_mode = MaskMode::Inside;
+        self.apply_render_state();
+    }
+
+    pub fn canvas_dimensions(&self) -> (u32, u32) {
+        (self.canvas_width, self.canvas_height)
+    }
+}
+
+/// Reorients `rect`, transforms it to its bounding box by `transform`,
+///   then intersects it with `parent`.
+fn clip_rect(mut rect: AABB<f32>, transform: Option<&glm::Mat3>, parent: Option<&AABB<f32>>) -> AABB<f32> {
+    rect.reorient();
+
+    if let Some(m) = transform {
+        let corners = [
+            m * glm::vec3(rect.c1.x, rect.c1.y, 1.),
+            m * glm::vec3(rect.c2.x, rect.c1.y, 1.),
+            m * glm::vec3(rect.c1.x, rect.c2.y, 1.),
+            m * glm::vec3(rect.c2.x, rect.c2.y, 1.),
+        ];
+        rect = AABB::new(f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for corner in corners.iter() {
+            rect.c1.x = rect.c1.x.min(corner.x);
+            rect.c1.y = rect.c1.y.min(corner.y);
+            rect.c2.x = rect.c2.x.max(corner.x);
+            rect.c2.y = rect.c2.y.max(corner.y);
+        }
+    }
+
+    if let Some(top) = parent {
+        rect.c1.x = rect.c1.x.max(top.c1.x);
+        rect.c1.y = rect.c1.y.max(top.c1.y);
+        rect.c2.x = rect.c2.x.min(top.c2.x);
+        rect.c2.y = rect.c2.y.min(top.c2.y);
+        // empty intersection
+        rect.c2.x = rect.c2.x.max(rect.c1.x);
+        rect.c2.y = rect.c2.y.max(rect.c1.y);
+    }
+
+    rect
+}
+
+/// Converts from top left canvas coordinates to bottom left GL coordinates.
+/// Works for both the window and `Canvas`es, as long as the canvas dimensions passed on bind are correct.
+fn clip_to_scissor(clip: &AABB<f32>, canvas_height: u32) -> ScissorRect {
+    let x1 = clip.c1.x.floor() as i32;
+    let y1 = clip.c1.y.floor() as i32;
+    let x2 = clip.c2.x.ceil() as i32;
+    let y2 = clip.c2.y.ceil() as i32;
+    ScissorRect {
+        x: x1,
+        y: canvas_height as i32 - y2,
+        width: x2 - x1,
+        height: y2 - y1,
+    }
+}
+
+// Each nested mask increments the stencil where it overlaps the mask below it,
+//   so a pixel is inside the current mask when its value equals the depth.
+fn mask_stencil(depth: u8, mode: MaskMode, writing: bool) -> Option<StencilState> {
+    let depth = depth as i32;
+    if writing {
+        Some(StencilState::write(CompareFunc::Equal, depth, StencilOp::Increment))
+    } else if depth > 0 {
+        match mode {
+            MaskMode::Inside  => Some(StencilState::test(CompareFunc::Equal, depth)),
+            MaskMode::Outside => Some(StencilState::test(CompareFunc::Equal, depth - 1)),
+        }
+    } else {
+        None
+    }
+}
+
+/// Decrements everything above the parent of the mask at `depth`.
+fn pop_mask_stencil(depth: u8) -> StencilState {
+    StencilState::write(CompareFunc::Less, depth as i32 - 1, StencilOp::Decrement)
+}
+
+/// Restores the default `RenderState`, so clips and masks don't leak into later drawing,
+///   including the stencil test and color mask of an unfinished mask.
+/// note: anything batched on top of this drawer should be drawn before it is dropped
+impl<'a> Drop for BoundDrawer2d<'a> {
+    fn drop(&mut self) {
+        debug_assert!(thread::panicking() || self.clip_stack.is_empty(),
+                      "drawer dropped with clips still pushed");
+        debug_assert!(thread::panicking() || (self.mask_depth == 0 && !self.mask_writing),
+                      "drawer dropped with masks still applied");
+        self.state_cache.apply(&RenderState::default());
+    }
 }
 
 //
@@ -190,6 +501,8 @@ impl<'a> BoundDrawer2d<'a> {
 pub struct BoundSpritebatch<'a> {
     base: BoundDrawer2d<'a>,
     sb: BoundInstancer<'a, SbSprite, Vertex2d>,
+    meshes: SpriteMeshes,
+    quad: MeshId,
 
     sprite_color: Color,
 }
@@ -228,12 +541,81 @@ impl<'a> BoundSpritebatch<'a> {
         self.base.locations()
     }
 
+    /// Draws anything batched with the previous state first.
+    pub fn set_render_state(&mut self, state: RenderState) {
+        if state != self.base.render_state {
+            self.draw_now();
+        }
+        self.base.set_render_state(state);
+    }
+
+    pub fn render_state(&self) -> &RenderState {
+        self.base.render_state()
+    }
+
+    pub fn push_clip(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, transformed: bool) {
+        self.draw_now();
+        self.base.push_clip(x1, y1, x2, y2, transformed);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.draw_now();
+        self.base.pop_clip();
+    }
+
+    pub fn begin_mask(&mut self) {
+        self.draw_now();
+        self.base.begin_mask();
+    }
+
+    pub fn end_mask(&mut self, mode: MaskMode) {
+        self.draw_now();
+        self.base.end_mask(mode);
+    }
+
+    pub fn set_mask_mode(&mut self, mode: MaskMode) {
+        self.draw_now();
+        self.base.set_mask_mode(mode);
+    }
+
+    pub fn mask_depth(&self) -> u8 {
+        self.base.mask_depth()
+    }
+
+    /// Removes the current mask, restoring the one below it.
+    pub fn pop_mask(&mut self) {
+        self.draw_now();
+        if self.base.begin_pop_mask() {
+            let (w, h) = self.base.canvas_dimensions();
+            self.sb.set_mesh(self.meshes.quad);
+            self.sb.push(SbSprite {
+                transform: Transform2d {
+                    scale: glm::vec2(w as f32, h as f32),
+                    .. Transform2d::identity()
+                },
+                .. Default::default()
+            });
+            self.draw_now();
+            self.base.end_pop_mask();
+        }
+    }
+
     //
 
     pub fn draw_now(&mut self) {
         self.sb.draw();
     }
 
+    /// Switches the mesh used by `sprite()`.
+    /// Draws anything already batched with a different mesh.
+    pub fn set_mesh(&mut self, mesh: MeshId) {
+        self.sb.set_mesh(mesh);
+    }
+
+    pub fn meshes(&self) -> SpriteMeshes {
+        self.meshes
+    }
+
     // TODO cleanup this api
     pub fn set_sprite_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
         self.sprite_color.r = r;
@@ -242,36 +624,86 @@ impl<'a> BoundSpritebatch<'a> {
         self.sprite_color.a = a;
     }
 
-    pub fn region(&mut self, uv: &UvRegion, x1: f32, y1: f32, x2: f32, y2: f32) {
+    /// Quad sprite covering `x1` `y1` to `x2` `y2` in the sprite color.
+    fn pull_rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> &mut SbSprite {
+        self.sb.set_mesh(self.quad);
+        let color = self.sprite_color;
         let sprite = self.sb.pull_default();
-        sprite.color = self.sprite_color;
-        sprite.uv = *uv;
+        sprite.color = color;
         sprite.transform.position.x = x1;
         sprite.transform.position.y = y1;
         sprite.transform.scale.x = x2 - x1;
         sprite.transform.scale.y = y2 - y1;
+        sprite
+    }
+
+    pub fn region(&mut self, uv: &UvRegion, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.pull_rectangle(x1, y1, x2, y2).uv = *uv;
+    }
+
+    /// Binds the gradient's texture, drawing anything batched with another texture.
+    pub fn gradient_rectangle(&mut self, gradient: &'a GradientTexture, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.set_texture(gradient.texture());
+        self.region(gradient.region(), x1, y1, x2, y2);
     }
 
     pub fn rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.pull_rectangle(x1, y1, x2, y2);
+    }
+
+    /// Colors go top left, top right, bottom right, bottom left on screen,
+    ///   and are multiplied by the sprite color.
+    /// Uses vertex colors, so unlike `gradient_rectangle()` the texture stays bound.
+    /// note: blends across the diagonal, use `GradientTexture::corners()` for a smooth blend
+    pub fn rectangle_corners(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, colors: &[Color; 4]) {
+        self.pull_rectangle(x1, y1, x2, y2).set_corner_colors(colors);
+    }
+
+    pub fn circle(&mut self, x: f32, y: f32, r: f32) {
+        self.sb.set_mesh(self.meshes.circle);
         let sprite = self.sb.pull_default();
         sprite.color = self.sprite_color;
-        sprite.transform.position.x = x1;
-        sprite.transform.position.y = y1;
-        sprite.transform.scale.x = x2 - x1;
-        sprite.transform.scale.y = y2 - y1;
+        sprite.transform.position.x = x;
+        sprite.transform.position.y = y;
+        sprite.transform.scale.x = r * 2.;
+        sprite.transform.scale.y = r * 2.;
     }
 
     pub fn print(&mut self, font: &'a BitmapFont, text: &str) {
         self.base.set_texture(font.texture());
+        self.sb.set_mesh(self.quad);
         self.sb.print(font, text);
         self.draw_now();
     }
 
+    /// Prints frame stats, one per line.
+    /// note: the overlay itself is counted in the current frame,
+    ///         so pass in the stats from `FrameStats::reset_frame()`
+    pub fn print_stats(&mut self, font: &'a BitmapFont, stats: &FrameStats) {
+        let line_height = font.line_height() + 1.;
+        let text = stats.to_string();
+        for (i, line) in text.lines().enumerate() {
+            self.push_coord(CoordinateTransform::Translate(glm::vec2(0., i as f32 * line_height)));
+            self.print(font, line);
+            self.pop_coord();
+        }
+    }
+
     pub fn sprite(&mut self, transform: Transform2d, uv: UvRegion, color: Color) {
-        let sprite = self.sb.pull();
-        sprite.transform = transform;
-        sprite.uv = uv;
-        sprite.color = color;
+        self.sb.set_mesh(self.quad);
+        self.sb.push(SbSprite {
+            uv,
+            transform,
+            color,
+            .. SbSprite::default()
+        });
+    }
+}
+
+impl<'a> Drop for BoundSpritebatch<'a> {
+    /// Draws before the base drawer restores the render state.
+    fn drop(&mut self) {
+        self.draw_now();
     }
 }
 
@@ -279,37 +711,395 @@ impl<'a> BoundSpritebatch<'a> {
 
 pub struct BoundShapeDrawer<'a> {
     base: BoundDrawer2d<'a>,
-    drawer: &'a ShapeDrawer,
+    drawer: &'a mut ShapeDrawer,
+    sdf_prog: Option<&'a Program2d>,
 }
 
 impl<'a> BoundShapeDrawer<'a> {
     // 'inherited'
     pub fn set_program(&mut self, prog: &'a Program2d) {
+        use std::ptr;
+
+        if !ptr::eq(self.base.prog, prog) {
+            self.draw_now();
+        }
         self.base.set_program(prog);
     }
 
+    /// Also stretches the whole texture over shapes again, after `set_gradient()`.
     pub fn set_texture(&mut self, texture: &'a Texture) {
+        use std::ptr;
+
+        if !ptr::eq(self.base.texture, texture) {
+            self.draw_now();
+        }
         self.base.set_texture(texture);
+        self.drawer.set_uv_region(UvRegion::new(0., 0., 1., 1.));
+    }
+
+    /// Fills shapes added after this with a gradient, stretched over each shape's bounding box.
+    /// Go back to plain colors by setting the texture to the shape drawer's white texture.
+    pub fn set_gradient(&mut self, gradient: &'a GradientTexture) {
+        self.set_texture(gradient.texture());
+        self.drawer.set_uv_region(*gradient.region());
     }
 
     pub fn push_coord(&mut self, t: CoordinateTransform) {
+        self.draw_now();
         self.base.push_coord(t);
+        self.drawer.set_scale(self.base.coordinate_scale());
     }
 
     pub fn pop_coord(&mut self) {
+        self.draw_now();
         self.base.pop_coord();
+        self.drawer.set_scale(self.base.coordinate_scale());
     }
 
     pub fn locations(&mut self) -> &Locations {
         self.base.locations()
     }
 
+    /// Draws anything batched with the previous state first.
+    pub fn set_render_state(&mut self, state: RenderState) {
+        if state != self.base.render_state {
+            self.draw_now();
+        }
+        self.base.set_render_state(state);
+    }
+
+    pub fn render_state(&self) -> &RenderState {
+        self.base.render_state()
+    }
+
+    pub fn push_clip(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, transformed: bool) {
+        self.draw_now();
+        self.base.push_clip(x1, y1, x2, y2, transformed);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.draw_now();
+        self.base.pop_clip();
+    }
+
+    pub fn begin_mask(&mut self) {
+        self.draw_now();
+        self.base.begin_mask();
+    }
+
+    pub fn end_mask(&mut self, mode: MaskMode) {
+        self.draw_now();
+        self.base.end_mask(mode);
+    }
+
+    pub fn set_mask_mode(&mut self, mode: MaskMode) {
+        self.draw_now();
+        self.base.set_mask_mode(mode);
+    }
+
+    pub fn mask_depth(&self) -> u8 {
+        self.base.mask_depth()
+    }
+
+    /// Removes the current mask, restoring the one below it.
+    pub fn pop_mask(&mut self) {
+        self.draw_now();
+        if self.base.begin_pop_mask() {
+            let (w, h) = self.base.canvas_dimensions();
+            self.drawer.filled_rectangle(0., 0., w as f32, h as f32);
+            self.draw_now();
+            self.base.end_pop_mask();
+        }
+    }
+
     //
 
-    // TODO add more functions
+    /// Draws everything batched so far.
+    pub fn draw_now(&mut self) {
+        self.drawer.flush(&self.base.prog.locs);
+        if let Some(prog) = self.sdf_prog {
+            if !self.drawer.sdf_is_empty() {
+                self.base.bind_secondary_program(prog);
+                self.drawer.flush_sdf(&prog.locs);
+                self.base.rebind_program();
+            }
+        }
+    }
+
+    /// When set, rectangles, rounded rectangles, circles, capsules and lines
+    ///   are drawn as signed distance fields with this program,
+    ///   otherwise they are tessellated.
+    /// `prog` should be like `DrawDefaults::sdf_program`.
+    pub fn set_sdf_program(&mut self, prog: Option<&'a Program2d>) {
+        self.draw_now();
+        self.sdf_prog = prog;
+    }
+
+    pub fn sdf_style_mut(&mut self) -> &mut SdfStyle {
+        self.drawer.sdf_style_mut()
+    }
+
+    // batches are drawn in order, switching between them draws the other one
+
+    fn begin_sdf(&mut self) -> bool {
+        if self.sdf_prog.is_some() {
+            if !self.drawer.is_empty() {
+                self.draw_now();
+            }
+            true
+        } else {
+            self.begin_tessellated();
+            false
+        }
+    }
+
+    fn begin_tessellated(&mut self) {
+        if !self.drawer.sdf_is_empty() {
+            self.draw_now();
+        }
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.drawer.set_color(color);
+    }
+
+    pub fn stroke_style_mut(&mut self) -> &mut StrokeStyle {
+        self.drawer.stroke_style_mut()
+    }
+
     pub fn rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
-        // self.base.shape_rectangle(x1, y1, x2, y2);
-        self.base.locations().set_rectangle(x1, y1, x2, y2);
-        self.drawer.draw_quad();
+        if self.begin_sdf() {
+            self.drawer.sdf_rectangle(x1, y1, x2, y2);
+        } else {
+            self.drawer.filled_rectangle(x1, y1, x2, y2);
+        }
+    }
+
+    pub fn circle(&mut self, x: f32, y: f32, r: f32) {
+        if self.begin_sdf() {
+            self.drawer.sdf_circle(x, y, r);
+        } else {
+            self.drawer.circle(x, y, r);
+        }
+    }
+
+    /// A line segment with round ends of radius `r`.
+    pub fn capsule(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, r: f32) {
+        if self.begin_sdf() {
+            self.drawer.sdf_capsule(x1, y1, x2, y2, r);
+        } else {
+            let style = *self.drawer.stroke_style();
+            *self.drawer.stroke_style_mut() = StrokeStyle {
+                thickness: r * 2.,
+                cap: LineCap::Round,
+                dash: None,
+                .. style
+            };
+            self.drawer.line(x1, y1, x2, y2);
+            *self.drawer.stroke_style_mut() = style;
+        }
+    }
+
+    pub fn ellipse(&mut self, x: f32, y: f32, rx: f32, ry: f32) {
+        self.begin_tessellated();
+        self.drawer.ellipse(x, y, rx, ry);
+    }
+
+    /// Max distance in pixels on screen that curves are allowed to stray from the true curve.
+    pub fn set_tolerance(&mut self, tolerance: f32) {
+        self.drawer.set_tolerance(tolerance);
+    }
+
+    pub fn triangle(&mut self, a: glm::Vec2, b: glm::Vec2, c: glm::Vec2) {
+        self.begin_tessellated();
+        self.drawer.triangle(a, b, c);
+    }
+
+    pub fn polygon(&mut self, points: &[glm::Vec2]) {
+        self.begin_tessellated();
+        self.drawer.polygon(points);
+    }
+
+    pub fn polygon_with_holes(&mut self, points: &[glm::Vec2], holes: &[&[glm::Vec2]]) {
+        self.begin_tessellated();
+        self.drawer.polygon_with_holes(points, holes);
+    }
+
+    pub fn filled_rectangle_corners(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, colors: &[Color; 4]) {
+        self.begin_tessellated();
+        self.drawer.filled_rectangle_corners(x1, y1, x2, y2, colors);
+    }
+
+    pub fn fill_path(&mut self, path: &Path, rule: FillRule) {
+        self.begin_tessellated();
+        self.drawer.fill_path(path, rule);
+    }
+
+    pub fn svg(&mut self, svg: &Svg) {
+        self.begin_tessellated();
+        self.drawer.svg(svg);
+    }
+
+    pub fn rounded_rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        if self.begin_sdf() {
+            self.drawer.sdf_rounded_rectangle(x1, y1, x2, y2, radius);
+        } else {
+            self.drawer.rounded_rectangle(x1, y1, x2, y2, radius);
+        }
+    }
+
+    /// note: dashes are ignored when drawn as a signed distance field
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        if self.begin_sdf() {
+            self.drawer.sdf_line(x1, y1, x2, y2);
+        } else {
+            self.drawer.line(x1, y1, x2, y2);
+        }
+    }
+
+    pub fn polyline(&mut self, points: &[glm::Vec2]) {
+        self.begin_tessellated();
+        self.drawer.polyline(points);
+    }
+
+    pub fn polygon_outline(&mut self, points: &[glm::Vec2]) {
+        self.begin_tessellated();
+        self.drawer.polygon_outline(points);
+    }
+
+    pub fn stroke_path(&mut self, path: &Path) {
+        self.begin_tessellated();
+        self.drawer.stroke_path(path);
+    }
+
+    pub fn rectangle_outline(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.begin_tessellated();
+        self.drawer.rectangle_outline(x1, y1, x2, y2);
+    }
+
+    pub fn rounded_rectangle_outline(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        self.begin_tessellated();
+        self.drawer.rounded_rectangle_outline(x1, y1, x2, y2, radius);
+    }
+
+    pub fn circle_outline(&mut self, x: f32, y: f32, r: f32) {
+        self.begin_tessellated();
+        self.drawer.circle_outline(x, y, r);
+    }
+
+    pub fn ellipse_outline(&mut self, x: f32, y: f32, rx: f32, ry: f32) {
+        self.begin_tessellated();
+        self.drawer.ellipse_outline(x, y, rx, ry);
+    }
+
+    pub fn arc(&mut self, x: f32, y: f32, r: f32, start: f32, sweep: f32) {
+        self.begin_tessellated();
+        self.drawer.arc(x, y, r, start, sweep);
+    }
+
+    pub fn pie_outline(&mut self, x: f32, y: f32, r: f32, start: f32, sweep: f32) {
+        self.begin_tessellated();
+        self.drawer.pie_outline(x, y, r, start, sweep);
+    }
+}
+
+impl<'a> Drop for BoundShapeDrawer<'a> {
+    fn drop(&mut self) {
+        self.draw_now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corners(rect: &AABB<f32>) -> [f32; 4] {
+        [rect.c1.x, rect.c1.y, rect.c2.x, rect.c2.y]
+    }
+
+    #[test]
+    fn clip_rect_reorients() {
+        let rect = clip_rect(AABB::new(10., 20., 0., 5.), None, None);
+        assert_eq!(corners(&rect), [0., 5., 10., 20.]);
+    }
+
+    #[test]
+    fn clip_rect_transforms_to_bounds() {
+        let m = glm::translation2d(&glm::vec2(100., 50.)) * glm::scaling2d(&glm::vec2(2., 2.));
+        let rect = clip_rect(AABB::new(0., 0., 10., 5.), Some(&m), None);
+        assert_eq!(corners(&rect), [100., 50., 120., 60.]);
+
+        // rotated a quarter turn, bounds of the rotated rectangle
+        let m = glm::rotation2d(std::f32::consts::FRAC_PI_2);
+        let rect = clip_rect(AABB::new(0., 0., 10., 5.), Some(&m), None);
+        let c = corners(&rect);
+        let expected = [-5., 0., 0., 10.];
+        for (a, b) in c.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 0.0001, "{:?} != {:?}", c, expected);
+        }
+    }
+
+    #[test]
+    fn clip_rect_intersects_parent() {
+        let parent = AABB::new(0., 0., 50., 50.);
+        let rect = clip_rect(AABB::new(25., -10., 100., 40.), None, Some(&parent));
+        assert_eq!(corners(&rect), [25., 0., 50., 40.]);
+
+        // disjoint clips are empty, not inverted
+        let rect = clip_rect(AABB::new(60., 60., 70., 70.), None, Some(&parent));
+        assert_eq!(rect.c1.x, rect.c2.x);
+        assert_eq!(rect.c1.y, rect.c2.y);
+        assert_eq!(clip_to_scissor(&rect, 100).width, 0);
+    }
+
+    #[test]
+    fn scissor_flips_y() {
+        let clip = AABB::new(10., 20., 30., 60.);
+        assert_eq!(clip_to_scissor(&clip, 100), ScissorRect {
+            x: 10,
+            y: 40,
+            width: 20,
+            height: 40,
+        });
+    }
+
+    #[test]
+    fn scissor_covers_partial_pixels() {
+        let clip = AABB::new(0.5, 0.25, 9.5, 9.75);
+        assert_eq!(clip_to_scissor(&clip, 10), ScissorRect {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        });
+    }
+
+    #[test]
+    fn mask_stencil_nesting() {
+        use MaskMode::*;
+
+        assert_eq!(mask_stencil(0, Inside, false), None);
+
+        // the outer mask increments 0 to 1
+        assert_eq!(mask_stencil(0, Inside, true),
+                   Some(StencilState::write(CompareFunc::Equal, 0, StencilOp::Increment)));
+        assert_eq!(mask_stencil(1, Inside, false),
+                   Some(StencilState::test(CompareFunc::Equal, 1)));
+        assert_eq!(mask_stencil(1, Outside, false),
+                   Some(StencilState::test(CompareFunc::Equal, 0)));
+
+        // a nested mask only increments inside the outer one
+        assert_eq!(mask_stencil(1, Inside, true),
+                   Some(StencilState::write(CompareFunc::Equal, 1, StencilOp::Increment)));
+        assert_eq!(mask_stencil(2, Inside, false),
+                   Some(StencilState::test(CompareFunc::Equal, 2)));
+        assert_eq!(mask_stencil(2, Outside, false),
+                   Some(StencilState::test(CompareFunc::Equal, 1)));
+
+        // popping it decrements everything above the outer mask back to 1
+        assert_eq!(pop_mask_stencil(2),
+                   StencilState::write(CompareFunc::Less, 1, StencilOp::Decrement));
+        assert_eq!(pop_mask_stencil(1),
+                   StencilState::write(CompareFunc::Less, 0, StencilOp::Decrement));
     }
 }
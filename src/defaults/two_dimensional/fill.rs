@@ -0,0 +1,695 @@
+use std::{
+    cmp::Ordering,
+    f32::consts,
+};
+
+use nalgebra_glm as glm;
+
+use super::arc_segments;
+
+//
+
+const EPSILON: f32 = 0.0001;
+
+/// Twice the signed area, positive if `o` `a` `b` turn counter clockwise on a y up plane.
+fn cross(o: glm::Vec2, a: glm::Vec2, b: glm::Vec2) -> f32 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+pub(crate) fn signed_area(points: &[glm::Vec2]) -> f32 {
+    let n = points.len();
+    (0..n).map(| i | {
+              let a = points[i];
+              let b = points[(i + 1) % n];
+              a.x * b.y - b.x * a.y
+          })
+          .sum::<f32>() / 2.
+}
+
+/// Works for either winding, points on the edges count as inside.
+fn in_triangle(p: glm::Vec2, a: glm::Vec2, b: glm::Vec2, c: glm::Vec2) -> bool {
+    let d1 = cross(a, b, p);
+    let d2 = cross(b, c, p);
+    let d3 = cross(c, a, p);
+    let has_neg = d1 < 0. || d2 < 0. || d3 < 0.;
+    let has_pos = d1 > 0. || d2 > 0. || d3 > 0.;
+    !(has_neg && has_pos)
+}
+
+/// Removes repeated points, including the last point if it closes the ring.
+pub(crate) fn dedup_ring(points: &[glm::Vec2]) -> Vec<glm::Vec2> {
+    let mut ret: Vec<glm::Vec2> = Vec::with_capacity(points.len());
+    for p in points {
+        let duplicate = match ret.last() {
+            Some(last) => glm::distance2(last, p) <= EPSILON * EPSILON,
+            None => false,
+        };
+        if !duplicate {
+            ret.push(*p);
+        }
+    }
+    if ret.len() > 1 && glm::distance2(&ret[0], &ret[ret.len() - 1]) <= EPSILON * EPSILON {
+        ret.pop();
+    }
+    ret
+}
+
+//
+
+/// Triangles of a convex polygon, appended to `out`.
+pub fn fill_convex(points: &[glm::Vec2], out: &mut Vec<glm::Vec2>) {
+    for i in 1..points.len().saturating_sub(1) {
+        out.push(points[0]);
+        out.push(points[i]);
+        out.push(points[i + 1]);
+    }
+}
+
+/// Triangulates a simple polygon with optional holes, appended to `out`.
+/// Polygons can be concave and have either winding,
+///   holes must be inside the polygon and not overlap each other.
+/// note: self intersecting polygons will not error, but will not fill correctly,
+///         use `fill_rings()` for them
+pub fn fill_polygon(points: &[glm::Vec2], holes: &[&[glm::Vec2]], out: &mut Vec<glm::Vec2>) {
+    let mut outer = dedup_ring(points);
+    if outer.len() < 3 {
+        return;
+    }
+    if signed_area(&outer) < 0. {
+        outer.reverse();
+    }
+
+    // holes are wound opposite to the outside
+    let mut holes: Vec<Vec<glm::Vec2>> = holes.iter()
+        .map(| hole | dedup_ring(hole))
+        .filter(| hole | hole.len() >= 3)
+        .map(| mut hole | {
+            if signed_area(&hole) > 0. {
+                hole.reverse();
+            }
+            hole
+        })
+        .collect();
+
+    // bridging the rightmost holes first keeps bridges from crossing each other
+    let max_x = | ring: &Vec<glm::Vec2> | ring.iter().fold(f32::NEG_INFINITY, | acc, p | acc.max(p.x));
+    holes.sort_by(| a, b | max_x(b).partial_cmp(&max_x(a)).unwrap_or(Ordering::Equal));
+
+    for hole in holes.iter() {
+        if let Some(merged) = bridge_hole(&outer, hole) {
+            outer = merged;
+        }
+    }
+
+    ear_clip(&outer, out);
+}
+
+/// Decides which areas enclosed by several rings are filled.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    /// Filled where a ray out of the shape crosses an odd number of rings.
+    EvenOdd,
+    /// Filled where the rings wind around a point a nonzero number of times.
+    #[default]
+    NonZero,
+}
+
+/// Even odd test, edges are not handled specially.
+fn point_in_ring(p: glm::Vec2, ring: &[glm::Vec2]) -> bool {
+    let n = ring.len();
+    let mut inside = false;
+    for i in 0..n {
+        let a = ring[i];
+        let b = ring[(i + n - 1) % n];
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Closing edge included.
+fn ring_edges(rings: &[Vec<glm::Vec2>]) -> Vec<(glm::Vec2, glm::Vec2)> {
+    rings.iter()
+        .filter(| ring | ring.len() >= 2)
+        .flat_map(| ring | {
+            let n = ring.len();
+            (0..n).map(move | i | (ring[i], ring[(i + 1) % n]))
+        })
+        .collect()
+}
+
+/// Where two edges cross, if they do.
+/// Edges that only touch at a point don't count.
+fn edge_crossing((a, b): (glm::Vec2, glm::Vec2), (c, d): (glm::Vec2, glm::Vec2)) -> Option<glm::Vec2> {
+    if a.x.max(b.x) < c.x.min(d.x) || c.x.max(d.x) < a.x.min(b.x) ||
+       a.y.max(b.y) < c.y.min(d.y) || c.y.max(d.y) < a.y.min(b.y) {
+        return None;
+    }
+    let d1 = cross(a, b, c);
+    let d2 = cross(a, b, d);
+    let d3 = cross(c, d, a);
+    let d4 = cross(c, d, b);
+    let crosses = ((d1 > EPSILON && d2 < -EPSILON) || (d1 < -EPSILON && d2 > EPSILON)) &&
+                  ((d3 > EPSILON && d4 < -EPSILON) || (d3 < -EPSILON && d4 > EPSILON));
+    if crosses {
+        Some(a + (b - a) * (d3 / (d3 - d4)))
+    } else {
+        None
+    }
+}
+
+/// Points where edges cross.
+/// Edges are swept top to bottom, only edges overlapping vertically are compared.
+fn crossings(edges: &[(glm::Vec2, glm::Vec2)]) -> Vec<glm::Vec2> {
+    let top = | (a, b): (glm::Vec2, glm::Vec2) | a.y.min(b.y);
+    let bottom = | (a, b): (glm::Vec2, glm::Vec2) | a.y.max(b.y);
+
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by(| &i, &j | top(edges[i]).partial_cmp(&top(edges[j])).unwrap_or(Ordering::Equal));
+
+    let mut ret = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    for i in order {
+        let edge = edges[i];
+        active.retain(| &j | bottom(edges[j]) >= top(edge));
+        ret.extend(active.iter().filter_map(| &j | edge_crossing(edges[j], edge)));
+        active.push(i);
+    }
+    ret
+}
+
+/// Whether any edges of the rings cross, within a ring or between rings.
+/// Edges that only touch at a point don't count.
+pub fn rings_cross(rings: &[Vec<glm::Vec2>]) -> bool {
+    let rings: Vec<_> = rings.iter().map(| ring | dedup_ring(ring)).collect();
+    !crossings(&ring_edges(&rings)).is_empty()
+}
+
+impl FillRule {
+    fn is_filled(self, winding: i32) -> bool {
+        match self {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+/// Triangulates a set of closed rings using a fill rule, appended to `out`.
+/// Rings can be nested any number of levels deep, and can cross each other or themselves.
+/// note: rings that don't cross are ear clipped,
+///         crossing rings are cut into horizontal strips at every point and crossing,
+///         which takes more triangles
+pub fn fill_rings(rings: &[Vec<glm::Vec2>], rule: FillRule, out: &mut Vec<glm::Vec2>) {
+    let rings: Vec<Vec<glm::Vec2>> = rings.iter()
+        .map(| ring | dedup_ring(ring))
+        .filter(| ring | ring.len() >= 3)
+        .collect();
+    let edges = ring_edges(&rings);
+    let crossings = crossings(&edges);
+
+    if crossings.is_empty() {
+        fill_nested(&rings, rule, out);
+    } else {
+        fill_strips(&edges, &crossings, rule, out);
+    }
+}
+
+/// Rings must not cross.
+fn fill_nested(rings: &[Vec<glm::Vec2>], rule: FillRule, out: &mut Vec<glm::Vec2>) {
+    let areas: Vec<f32> = rings.iter().map(| ring | signed_area(ring)).collect();
+
+    // the smallest ring containing each ring
+    let parents: Vec<Option<usize>> = (0..rings.len())
+        .map(| i | {
+            (0..rings.len())
+                .filter(| &j | j != i && areas[j].abs() > areas[i].abs())
+                .filter(| &j | point_in_ring(rings[i][0], &rings[j]))
+                .min_by(| &a, &b | {
+                    areas[a].abs().partial_cmp(&areas[b].abs()).unwrap_or(Ordering::Equal)
+                })
+        })
+        .collect();
+
+    // winding number just inside each ring
+    let mut windings = vec![0; rings.len()];
+    let mut depths = vec![0; rings.len()];
+    for i in 0..rings.len() {
+        let mut at = Some(i);
+        while let Some(j) = at {
+            windings[i] += if areas[j] > 0. { 1 } else { -1 };
+            depths[i] += 1;
+            at = parents[j];
+        }
+    }
+
+    let filled = | i: Option<usize> | match i {
+        Some(i) => match rule {
+            FillRule::EvenOdd => depths[i] % 2 == 1,
+            FillRule::NonZero => windings[i] != 0,
+        },
+        None => false,
+    };
+
+    // rings where the fill changes are edges of the shape,
+    //   holes belong to the closest outer edge containing them
+    let outer_of = | mut i: usize | loop {
+        match parents[i] {
+            Some(j) if filled(Some(j)) && !filled(parents[j]) => break Some(j),
+            Some(j) => i = j,
+            None => break None,
+        }
+    };
+
+    for i in 0..rings.len() {
+        if !filled(Some(i)) || filled(parents[i]) {
+            continue;
+        }
+        let holes: Vec<&[glm::Vec2]> = (0..rings.len())
+            .filter(| &j | !filled(Some(j)) && filled(parents[j]) && outer_of(j) == Some(i))
+            .map(| j | &rings[j][..])
+            .collect();
+        fill_polygon(&rings[i], &holes, out);
+    }
+}
+
+/// Cuts the shape into horizontal strips at every point and crossing,
+///   so no edges cross inside a strip,
+///   then fills between edges left to right by the winding number.
+fn fill_strips(edges: &[(glm::Vec2, glm::Vec2)],
+               crossings: &[glm::Vec2],
+               rule: FillRule,
+               out: &mut Vec<glm::Vec2>
+) {
+    let mut ys: Vec<f32> = edges.iter()
+        .map(| (a, _) | a.y)
+        .chain(crossings.iter().map(| p | p.y))
+        .collect();
+    ys.sort_by(| a, b | a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    ys.dedup_by(| a, b | (*a - *b).abs() <= EPSILON);
+
+    let x_at = | (a, b): (glm::Vec2, glm::Vec2), y: f32 | a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x);
+
+    for strip in ys.windows(2) {
+        let (y1, y2) = (strip[0], strip[1]);
+        let mid = (y1 + y2) / 2.;
+
+        // (x at mid, x at top, x at bottom, winding direction)
+        let mut crossed: Vec<(f32, f32, f32, i32)> = edges.iter()
+            .filter(| (a, b) | a.y.min(b.y) < mid && a.y.max(b.y) > mid)
+            .map(| &(a, b) | {
+                let dir = if b.y > a.y { 1 } else { -1 };
+                (x_at((a, b), mid), x_at((a, b), y1), x_at((a, b), y2), dir)
+            })
+            .collect();
+        crossed.sort_by(| a, b | a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut winding = 0;
+        let mut left = None;
+        for (_, x1, x2, dir) in crossed {
+            let was_filled = rule.is_filled(winding);
+            winding += dir;
+            match (was_filled, rule.is_filled(winding)) {
+                (false, true) => left = Some((x1, x2)),
+                (true, false) => {
+                    if let Some((left_x1, left_x2)) = left.take() {
+                        let a = glm::vec2(left_x1, y1);
+                        let b = glm::vec2(x1, y1);
+                        let c = glm::vec2(x2, y2);
+                        let d = glm::vec2(left_x2, y2);
+                        // strips ending at a crossing are triangles
+                        if x1 - left_x1 > EPSILON {
+                            out.extend_from_slice(&[a, b, c]);
+                        }
+                        if x2 - left_x2 > EPSILON {
+                            out.extend_from_slice(&[a, c, d]);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Connects a hole to the outside with a zero width bridge, making one ring.
+/// Returns `None` if the hole is not inside the polygon.
+fn bridge_hole(outer: &[glm::Vec2], hole: &[glm::Vec2]) -> Option<Vec<glm::Vec2>> {
+    let (hole_at, m) = hole.iter()
+        .copied()
+        .enumerate()
+        .fold((0, hole[0]), | acc, (i, p) | if p.x > acc.1.x { (i, p) } else { acc });
+
+    // cast a ray to the right of m, find the closest edge it hits
+    let n = outer.len();
+    let mut hit: Option<(f32, usize)> = None;
+    for i in 0..n {
+        let a = outer[i];
+        let b = outer[(i + 1) % n];
+        if a.y == b.y || m.y < a.y.min(b.y) || m.y > a.y.max(b.y) {
+            continue;
+        }
+        let x = a.x + (m.y - a.y) / (b.y - a.y) * (b.x - a.x);
+        if x < m.x {
+            continue;
+        }
+        let closer = match hit {
+            Some((hit_x, _)) => x < hit_x,
+            None => true,
+        };
+        if closer {
+            let candidate = if a.x > b.x { i } else { (i + 1) % n };
+            hit = Some((x, candidate));
+        }
+    }
+
+    let (hit_x, mut at) = hit?;
+    let hit_point = glm::vec2(hit_x, m.y);
+    let p = outer[at];
+
+    // a reflex vertex inside (m, hit, p) would block the bridge,
+    //   use the one closest in angle to the ray instead
+    if glm::distance2(&p, &hit_point) > EPSILON * EPSILON {
+        let mut best = (p.y - m.y).abs().atan2(p.x - m.x);
+        for j in 0..n {
+            let v = outer[j];
+            if j == at || v.x < m.x {
+                continue;
+            }
+            let prev = outer[(j + n - 1) % n];
+            let next = outer[(j + 1) % n];
+            let reflex = cross(prev, v, next) <= 0.;
+            if reflex && in_triangle(v, m, hit_point, p) {
+                let angle = (v.y - m.y).abs().atan2(v.x - m.x);
+                if angle < best {
+                    best = angle;
+                    at = j;
+                }
+            }
+        }
+    }
+
+    let mut ret = Vec::with_capacity(n + hole.len() + 2);
+    ret.extend_from_slice(&outer[..=at]);
+    ret.extend_from_slice(&hole[hole_at..]);
+    ret.extend_from_slice(&hole[..=hole_at]);
+    ret.extend_from_slice(&outer[at..]);
+    Some(ret)
+}
+
+/// Ring must be wound counter clockwise.
+fn ear_clip(points: &[glm::Vec2], out: &mut Vec<glm::Vec2>) {
+    let mut ring: Vec<usize> = (0..points.len()).collect();
+    let mut i = 0;
+    let mut misses = 0;
+
+    while ring.len() > 3 {
+        let len = ring.len();
+        let prev = (i + len - 1) % len;
+        let next = (i + 1) % len;
+        let (a, b, c) = (points[ring[prev]], points[ring[i]], points[ring[next]]);
+        let turn = cross(a, b, c);
+
+        if turn.abs() < EPSILON {
+            // collinear, nothing to fill
+            ring.remove(i);
+            misses = 0;
+        } else if turn > 0. && is_ear(points, &ring, prev, i, next) {
+            out.extend_from_slice(&[a, b, c]);
+            ring.remove(i);
+            misses = 0;
+        } else {
+            misses += 1;
+            if misses > len {
+                // no ears left, polygon is degenerate or self intersecting
+                out.extend_from_slice(&[a, b, c]);
+                ring.remove(i);
+                misses = 0;
+            } else {
+                i += 1;
+            }
+        }
+        i %= ring.len();
+    }
+
+    if ring.len() == 3 {
+        let (a, b, c) = (points[ring[0]], points[ring[1]], points[ring[2]]);
+        if cross(a, b, c).abs() >= EPSILON {
+            out.extend_from_slice(&[a, b, c]);
+        }
+    }
+}
+
+fn is_ear(points: &[glm::Vec2], ring: &[usize], prev: usize, at: usize, next: usize) -> bool {
+    let (a, b, c) = (points[ring[prev]], points[ring[at]], points[ring[next]]);
+    ring.iter()
+        .enumerate()
+        .filter(| (j, _) | *j != prev && *j != at && *j != next)
+        .map(| (_, idx) | points[*idx])
+        // bridges duplicate points
+        .filter(| p | *p != a && *p != b && *p != c)
+        .all(| p | !in_triangle(p, a, b, c))
+}
+
+//
+
+/// Outline of a rounded rectangle, wound clockwise on screen.
+/// `radius` is clamped to fit, corners stay within `tolerance` of a true arc.
+pub fn rounded_rect_points(x1: f32,
+                           y1: f32,
+                           x2: f32,
+                           y2: f32,
+                           radius: f32,
+                           tolerance: f32,
+                           out: &mut Vec<glm::Vec2>
+) {
+    let (x1, x2) = (x1.min(x2), x1.max(x2));
+    let (y1, y2) = (y1.min(y2), y1.max(y2));
+    let r = radius.min((x2 - x1) / 2.).min((y2 - y1) / 2.).max(0.);
+
+    let corners = [
+        (glm::vec2(x1 + r, y1 + r), consts::PI),
+        (glm::vec2(x2 - r, y1 + r), consts::PI * 1.5),
+        (glm::vec2(x2 - r, y2 - r), 0.),
+        (glm::vec2(x1 + r, y2 - r), consts::PI * 0.5),
+    ];
+
+    if r <= 0. {
+        out.extend(corners.iter().map(| (p, _) | *p));
+        return;
+    }
+
+    let segments = arc_segments(r, consts::PI / 2., tolerance);
+    for (center, start) in corners.iter() {
+        for i in 0..=segments {
+            let at = start + consts::PI / 2. * i as f32 / segments as f32;
+            out.push(center + glm::vec2(at.cos(), at.sin()) * r);
+        }
+    }
+}
+
+/// Points along an elliptical arc, angles in radians.
+/// Positive `sweep` goes clockwise on screen.
+/// Segments stay within `tolerance` of the arc.
+pub fn ellipse_points(center: glm::Vec2,
+                      rx: f32,
+                      ry: f32,
+                      start: f32,
+                      sweep: f32,
+                      tolerance: f32,
+                      out: &mut Vec<glm::Vec2>
+) {
+    let segments = arc_segments(rx.max(ry), sweep, tolerance);
+    for i in 0..=segments {
+        let at = start + sweep * i as f32 / segments as f32;
+        out.push(center + glm::vec2(at.cos() * rx, at.sin() * ry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::area;
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<glm::Vec2> {
+        vec![
+            glm::vec2(x, y),
+            glm::vec2(x + size, y),
+            glm::vec2(x + size, y + size),
+            glm::vec2(x, y + size),
+        ]
+    }
+
+    fn reversed(mut ring: Vec<glm::Vec2>) -> Vec<glm::Vec2> {
+        ring.reverse();
+        ring
+    }
+
+    #[test]
+    fn either_winding() {
+        for ring in &[square(0., 0., 10.), reversed(square(0., 0., 10.))] {
+            let mut out = Vec::new();
+            fill_polygon(ring, &[], &mut out);
+            assert_eq!(out.len(), 6);
+            assert!((area(&out) - 100.).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn concave() {
+        // L shape
+        let ring = [
+            glm::vec2(0., 0.),
+            glm::vec2(10., 0.),
+            glm::vec2(10., 4.),
+            glm::vec2(4., 4.),
+            glm::vec2(4., 10.),
+            glm::vec2(0., 10.),
+        ];
+        let mut out = Vec::new();
+        fill_polygon(&ring, &[], &mut out);
+        assert!((area(&out) - 64.).abs() < 0.001);
+    }
+
+    #[test]
+    fn collinear_and_duplicate_points() {
+        let ring = [
+            glm::vec2(0., 0.),
+            glm::vec2(5., 0.),
+            glm::vec2(5., 0.),
+            glm::vec2(10., 0.),
+            glm::vec2(10., 10.),
+            glm::vec2(10., 10.),
+            glm::vec2(5., 10.),
+            glm::vec2(0., 10.),
+            glm::vec2(0., 0.),
+        ];
+        let mut out = Vec::new();
+        fill_polygon(&ring, &[], &mut out);
+        assert!((area(&out) - 100.).abs() < 0.001);
+        // collinear points are dropped rather than making zero area triangles
+        assert!(out.chunks(3).all(| t | cross(t[0], t[1], t[2]).abs() >= EPSILON));
+    }
+
+    #[test]
+    fn degenerate_rings() {
+        let mut out = Vec::new();
+        fill_polygon(&[], &[], &mut out);
+        fill_polygon(&[glm::vec2(0., 0.), glm::vec2(1., 1.)], &[], &mut out);
+        fill_polygon(&[glm::vec2(0., 0.), glm::vec2(1., 1.), glm::vec2(2., 2.)], &[], &mut out);
+        fill_polygon(&[glm::vec2(1., 1.); 5], &[], &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn holes_either_winding() {
+        for hole in &[square(3., 3., 4.), reversed(square(3., 3., 4.))] {
+            for outer in &[square(0., 0., 10.), reversed(square(0., 0., 10.))] {
+                let mut out = Vec::new();
+                fill_polygon(outer, &[hole], &mut out);
+                assert!((area(&out) - 84.).abs() < 0.001);
+            }
+        }
+    }
+
+    #[test]
+    fn fill_rules() {
+        let outer = square(0., 0., 10.);
+        let same = square(3., 3., 4.);
+        let opposite = reversed(square(3., 3., 4.));
+
+        let fill = | rings: &[Vec<glm::Vec2>], rule | {
+            let mut out = Vec::new();
+            fill_rings(rings, rule, &mut out);
+            area(&out)
+        };
+
+        assert!((fill(&[outer.clone(), same.clone()], FillRule::EvenOdd) - 84.).abs() < 0.001);
+        assert!((fill(&[outer.clone(), opposite.clone()], FillRule::EvenOdd) - 84.).abs() < 0.001);
+        assert!((fill(&[outer.clone(), same], FillRule::NonZero) - 100.).abs() < 0.001);
+        assert!((fill(&[outer, opposite], FillRule::NonZero) - 84.).abs() < 0.001);
+    }
+
+    #[test]
+    fn crossing_rings() {
+        let outer = square(0., 0., 10.);
+        assert!(!rings_cross(&[outer.clone(), square(3., 3., 4.)]));
+        // sharing a corner is touching, not crossing
+        assert!(!rings_cross(&[outer.clone(), square(10., 10., 4.)]));
+        assert!(rings_cross(&[outer, square(8., 3., 4.)]));
+
+        let bowtie = vec![glm::vec2(0., 0.), glm::vec2(10., 10.), glm::vec2(10., 0.), glm::vec2(0., 10.)];
+        assert!(rings_cross(&[bowtie]));
+    }
+
+    #[test]
+    fn crossing_points() {
+        let edges = [
+            (glm::vec2(0., 0.), glm::vec2(10., 10.)),
+            (glm::vec2(10., 0.), glm::vec2(0., 10.)),
+            (glm::vec2(20., 0.), glm::vec2(20., 10.)),
+        ];
+        let points = crossings(&edges);
+        assert_eq!(points.len(), 1);
+        assert!(glm::distance(&points[0], &glm::vec2(5., 5.)) < 0.001);
+    }
+
+    fn fill_area(rings: &[Vec<glm::Vec2>], rule: FillRule) -> f32 {
+        let mut out = Vec::new();
+        fill_rings(rings, rule, &mut out);
+        area(&out)
+    }
+
+    #[test]
+    fn bowtie() {
+        let bowtie = vec![glm::vec2(0., 0.), glm::vec2(10., 10.), glm::vec2(10., 0.), glm::vec2(0., 10.)];
+        for rule in &[FillRule::EvenOdd, FillRule::NonZero] {
+            assert!((fill_area(&[bowtie.clone()], *rule) - 50.).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn overlapping_rings() {
+        let a = square(0., 0., 10.);
+        let b = square(5., 5., 10.);
+
+        // union is 175, overlap is 25
+        assert!((fill_area(&[a.clone(), b.clone()], FillRule::NonZero) - 175.).abs() < 0.001);
+        assert!((fill_area(&[a.clone(), b.clone()], FillRule::EvenOdd) - 150.).abs() < 0.001);
+        // wound opposite, the overlap winds 0
+        assert!((fill_area(&[a.clone(), reversed(b.clone())], FillRule::NonZero) - 150.).abs() < 0.001);
+        assert!((fill_area(&[a, reversed(b)], FillRule::EvenOdd) - 150.).abs() < 0.001);
+    }
+
+    #[test]
+    fn star() {
+        let point = | i: usize, r: f32 | {
+            let at = consts::PI * 2. * i as f32 / 5. - consts::PI / 2.;
+            glm::vec2(at.cos(), at.sin()) * r
+        };
+        // every other point of a pentagon, crossing itself 5 times
+        let star: Vec<_> = (0..5).map(| i | point((i * 2) % 5, 10.)).collect();
+        assert!(rings_cross(&[star.clone()]));
+
+        // the outline without crossings, and the pentagon in the middle
+        let inner_r = 10. * (consts::PI * 2. / 5.).cos() / (consts::PI / 5.).cos();
+        let outline: Vec<_> = (0..10)
+            .map(| i | {
+                let at = consts::PI * i as f32 / 5. - consts::PI / 2.;
+                let r = if i % 2 == 0 { 10. } else { inner_r };
+                glm::vec2(at.cos(), at.sin()) * r
+            })
+            .collect();
+        let middle: Vec<_> = (0..5)
+            .map(| i | {
+                let at = consts::PI * (2 * i + 1) as f32 / 5. - consts::PI / 2.;
+                glm::vec2(at.cos(), at.sin()) * inner_r
+            })
+            .collect();
+        let outline_area = signed_area(&outline).abs();
+        let middle_area = signed_area(&middle).abs();
+
+        assert!((fill_area(&[star.clone()], FillRule::NonZero) - outline_area).abs() < 0.01);
+        assert!((fill_area(&[star], FillRule::EvenOdd) - (outline_area - middle_area)).abs() < 0.01);
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    cmp::Ordering,
+    f32::consts,
+};
+
+use image::{
+    RgbaImage,
+    Rgba,
+};
+use nalgebra_glm as glm;
+
+use crate::{
+    gfx::{
+        Texture,
+        UvRegion,
+    },
+    math::Color,
+};
+
+//
+
+#[derive(Copy, Clone, Debug)]
+pub struct ColorStop {
+    /// 0 to 1 along the gradient.
+    pub offset: f32,
+    pub color: Color,
+}
+
+/// Positions are in uv space, 0 to 1 across whatever the gradient is stretched over,
+///   with 0, 0 in the top left on screen.
+#[derive(Copy, Clone, Debug)]
+pub enum GradientKind {
+    /// Along the line from `start` to `end`.
+    Linear {
+        start: glm::Vec2,
+        end: glm::Vec2,
+    },
+    /// Outwards from `center`, reaching the last stop at `radius`.
+    Radial {
+        center: glm::Vec2,
+        radius: f32,
+    },
+    /// Around `center`, clockwise on screen, starting at `angle` in radians.
+    Conic {
+        center: glm::Vec2,
+        angle: f32,
+    },
+}
+
+/// Color gradient with any number of stops.
+/// Colors before the first stop and after the last are held.
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<ColorStop>,
+}
+
+impl Gradient {
+    pub fn new(kind: GradientKind) -> Self {
+        Self {
+            kind,
+            stops: Vec::new(),
+        }
+    }
+
+    pub fn linear(start: glm::Vec2, end: glm::Vec2) -> Self {
+        Self::new(GradientKind::Linear { start, end })
+    }
+
+    pub fn radial(center: glm::Vec2, radius: f32) -> Self {
+        Self::new(GradientKind::Radial { center, radius })
+    }
+
+    pub fn conic(center: glm::Vec2, angle: f32) -> Self {
+        Self::new(GradientKind::Conic { center, angle })
+    }
+
+    /// Stops can be added in any order.
+    pub fn with_stop(mut self, offset: f32, color: Color) -> Self {
+        self.stops.push(ColorStop {
+            offset,
+            color,
+        });
+        self.stops.sort_by(| a, b | a.offset.partial_cmp(&b.offset).unwrap_or(Ordering::Equal));
+        self
+    }
+
+    /// Where `uv` falls along the gradient, 0 to 1 between the ends.
+    pub fn offset_at(&self, uv: glm::Vec2) -> f32 {
+        match self.kind {
+            GradientKind::Linear { start, end } => {
+                let dir = end - start;
+                let len2 = dir.norm_squared();
+                if len2 > 0. {
+                    glm::dot(&(uv - start), &dir) / len2
+                } else {
+                    0.
+                }
+            }
+            GradientKind::Radial { center, radius } => {
+                if radius > 0. {
+                    glm::distance(&uv, &center) / radius
+                } else {
+                    1.
+                }
+            }
+            GradientKind::Conic { center, angle } => {
+                let d = uv - center;
+                (d.y.atan2(d.x) - angle).rem_euclid(consts::PI * 2.) / (consts::PI * 2.)
+            }
+        }
+    }
+
+    /// Color at an offset along the gradient, transparent if there are no stops.
+    pub fn color_at_offset(&self, offset: f32) -> Color {
+        let first = match self.stops.first() {
+            Some(first) => first,
+            None => return Color::new_rgba(0., 0., 0., 0.),
+        };
+        if offset <= first.offset {
+            return first.color;
+        }
+
+        for pair in self.stops.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if offset <= b.offset {
+                let span = b.offset - a.offset;
+                let t = if span > 0. { (offset - a.offset) / span } else { 1. };
+                return lerp_color(a.color, b.color, t);
+            }
+        }
+
+        self.stops[self.stops.len() - 1].color
+    }
+
+    pub fn color_at(&self, uv: glm::Vec2) -> Color {
+        self.color_at_offset(self.offset_at(uv))
+    }
+
+    /// Samples the gradient at the center of every pixel.
+    pub fn to_image(&self, width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, | x, y | {
+            let uv = glm::vec2((x as f32 + 0.5) / width as f32,
+                               (y as f32 + 0.5) / height as f32);
+            Rgba::from(self.color_at(uv))
+        })
+    }
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::new_rgba(a.r + (b.r - a.r) * t,
+                    a.g + (b.g - a.g) * t,
+                    a.b + (b.b - a.b) * t,
+                    a.a + (b.a - a.a) * t)
+}
+
+//
+
+/// A gradient baked into a texture, and the region of it to draw.
+///
+/// Draw with `BoundSpritebatch::gradient_rectangle()`,
+///   or with `BoundShapeDrawer::set_gradient()`,
+///   which stretches it over each shape's bounding box.
+pub struct GradientTexture {
+    texture: Texture,
+    region: UvRegion,
+}
+
+impl GradientTexture {
+    /// Bigger textures show less banding for radial and conic gradients,
+    ///   linear gradients only need to be big along their direction.
+    pub fn new(gradient: &Gradient, width: u32, height: u32) -> Self {
+        let mut texture = Texture::new(&gradient.to_image(width.max(1), height.max(1)));
+        texture.set_wrap(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE);
+        texture.set_filter(gl::LINEAR, gl::LINEAR);
+        Self {
+            texture,
+            region: UvRegion::new(0., 0., 1., 1.),
+        }
+    }
+
+    /// Blends bilinearly between four corner colors,
+    ///   in order top left, top right, bottom right, bottom left on screen.
+    /// The texture is set to linear filtering, changing its filter breaks the blend.
+    /// note: drawing this binds its texture, for a blend across the diagonal without a texture switch
+    ///         use `BoundSpritebatch::rectangle_corners()` or `BoundShapeDrawer::filled_rectangle_corners()`
+    pub fn corners(colors: &[Color; 4]) -> Self {
+        let mut image = RgbaImage::new(2, 2);
+        image.put_pixel(0, 0, Rgba::from(colors[0]));
+        image.put_pixel(1, 0, Rgba::from(colors[1]));
+        image.put_pixel(1, 1, Rgba::from(colors[2]));
+        image.put_pixel(0, 1, Rgba::from(colors[3]));
+
+        let mut texture = Texture::new(&image);
+        texture.set_wrap(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE);
+        // linear filtering between the pixel centers is a bilinear blend
+        texture.set_filter(gl::LINEAR, gl::LINEAR);
+        Self {
+            texture,
+            region: UvRegion::new(0.25, 0.25, 0.75, 0.75),
+        }
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn region(&self) -> &UvRegion {
+        &self.region
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.0001
+    }
+
+    fn gray(v: f32) -> Color {
+        Color::new_rgba(v, v, v, 1.)
+    }
+
+    #[test]
+    fn stops_are_sorted() {
+        let gradient = Gradient::linear(glm::vec2(0., 0.), glm::vec2(1., 0.))
+            .with_stop(1., gray(1.))
+            .with_stop(0., gray(0.))
+            .with_stop(0.5, gray(0.2));
+        let offsets: Vec<f32> = gradient.stops.iter().map(| s | s.offset).collect();
+        assert_eq!(offsets, vec![0., 0.5, 1.]);
+    }
+
+    #[test]
+    fn color_between_stops() {
+        let gradient = Gradient::linear(glm::vec2(0., 0.), glm::vec2(1., 0.))
+            .with_stop(0.2, gray(0.))
+            .with_stop(0.6, gray(1.));
+        assert!(close(gradient.color_at_offset(0.4).r, 0.5));
+        assert!(close(gradient.color_at_offset(0.3).r, 0.25));
+
+        // ends are held
+        assert!(close(gradient.color_at_offset(-1.).r, 0.));
+        assert!(close(gradient.color_at_offset(0.1).r, 0.));
+        assert!(close(gradient.color_at_offset(0.9).r, 1.));
+        assert!(close(gradient.color_at_offset(2.).r, 1.));
+    }
+
+    #[test]
+    fn hard_stops() {
+        // two stops at the same offset switch colors without a blend
+        let gradient = Gradient::linear(glm::vec2(0., 0.), glm::vec2(1., 0.))
+            .with_stop(0., gray(0.))
+            .with_stop(0.5, gray(0.))
+            .with_stop(0.5, gray(1.))
+            .with_stop(1., gray(1.));
+        assert!(close(gradient.color_at_offset(0.49).r, 0.));
+        assert!(close(gradient.color_at_offset(0.5).r, 0.));
+        assert!(close(gradient.color_at_offset(0.51).r, 1.));
+    }
+
+    #[test]
+    fn empty_and_single_stop() {
+        let gradient = Gradient::radial(glm::vec2(0.5, 0.5), 0.5);
+        let color = gradient.color_at_offset(0.5);
+        assert!(close(color.a, 0.));
+
+        let gradient = gradient.with_stop(0.5, gray(0.3));
+        assert!(close(gradient.color_at_offset(0.).r, 0.3));
+        assert!(close(gradient.color_at_offset(1.).r, 0.3));
+    }
+
+    #[test]
+    fn linear_offsets() {
+        let gradient = Gradient::linear(glm::vec2(0.25, 0.), glm::vec2(0.75, 0.));
+        assert!(close(gradient.offset_at(glm::vec2(0.25, 0.7)), 0.));
+        assert!(close(gradient.offset_at(glm::vec2(0.5, 0.2)), 0.5));
+        assert!(close(gradient.offset_at(glm::vec2(1., 0.)), 1.5));
+        assert!(close(gradient.offset_at(glm::vec2(0., 0.)), -0.5));
+
+        let degenerate = Gradient::linear(glm::vec2(0.5, 0.5), glm::vec2(0.5, 0.5));
+        assert!(close(degenerate.offset_at(glm::vec2(1., 1.)), 0.));
+    }
+
+    #[test]
+    fn radial_offsets() {
+        let gradient = Gradient::radial(glm::vec2(0.5, 0.5), 0.5);
+        assert!(close(gradient.offset_at(glm::vec2(0.5, 0.5)), 0.));
+        assert!(close(gradient.offset_at(glm::vec2(0.5, 0.25)), 0.5));
+        assert!(close(gradient.offset_at(glm::vec2(1., 0.5)), 1.));
+
+        let degenerate = Gradient::radial(glm::vec2(0.5, 0.5), 0.);
+        assert!(close(degenerate.offset_at(glm::vec2(0.5, 0.5)), 1.));
+    }
+
+    #[test]
+    fn conic_offsets() {
+        let center = glm::vec2(0.5, 0.5);
+
+        // clockwise on screen, y is down
+        let gradient = Gradient::conic(center, 0.);
+        assert!(close(gradient.offset_at(glm::vec2(1., 0.5)), 0.));
+        assert!(close(gradient.offset_at(glm::vec2(0.5, 1.)), 0.25));
+        assert!(close(gradient.offset_at(glm::vec2(0., 0.5)), 0.5));
+        assert!(close(gradient.offset_at(glm::vec2(0.5, 0.)), 0.75));
+
+        // offsets before the start angle wrap around instead of going negative
+        let gradient = Gradient::conic(center, consts::PI / 2.);
+        assert!(close(gradient.offset_at(glm::vec2(0.5, 1.)), 0.));
+        assert!(close(gradient.offset_at(glm::vec2(1., 0.5)), 0.75));
+        let gradient = Gradient::conic(center, -consts::PI * 3.);
+        assert!(close(gradient.offset_at(glm::vec2(0., 0.5)), 0.));
+    }
+
+    #[test]
+    fn image_samples_pixel_centers() {
+        let gradient = Gradient::linear(glm::vec2(0., 0.), glm::vec2(1., 0.))
+            .with_stop(0., gray(0.))
+            .with_stop(1., gray(1.));
+        let image = gradient.to_image(2, 3);
+        assert_eq!(image.dimensions(), (2, 3));
+        for y in 0..3 {
+            assert_eq!(image.get_pixel(0, y), &Rgba::from([63, 63, 63, 255]));
+            assert_eq!(image.get_pixel(1, y), &Rgba::from([191, 191, 191, 255]));
+        }
+    }
+}
@@ -10,6 +10,21 @@ pub use spritebatch::*;
 mod bitmap_font;
 pub use bitmap_font::*;
 
+mod stroke;
+pub use stroke::*;
+
+mod fill;
+pub use fill::*;
+
+mod path;
+pub use path::*;
+
+mod svg;
+pub use svg::*;
+
+mod gradient;
+pub use gradient::*;
+
 mod shape_drawer;
 pub use shape_drawer::*;
 
@@ -21,6 +36,7 @@ pub use program2d::*;
 
 //
 
+use gl::types::GLuint;
 use image;
 
 use crate::{
@@ -55,6 +71,14 @@ mod shaders {
     pub const DEFAULT_SB_FRAG: &str =
         include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
                              "/content/shaders/spritebatch.incl.frag"));
+
+    pub const DEFAULT_SDF_VERT: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
+                             "/content/shaders/sdf.incl.vert"));
+
+    pub const DEFAULT_SDF_FRAG: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
+                             "/content/shaders/sdf.incl.frag"));
 }
 
 mod images {
@@ -82,6 +106,20 @@ fn parse_shader_template<'a: 'b, 'b>(template: &'a str, effect: Option<&'b str>)
     ret
 }
 
+/// Location of the per vertex color attribute in the default shaders.
+pub const VERTEX_COLOR_LOCATION: GLuint = 7;
+
+/// Sets the vertex color attribute's current value to white.
+/// Meshes without a color attribute read this value instead, which starts out black.
+/// note: called by `DrawDefaults::new()`, the value is context state and survives program binds
+pub fn init_vertex_color() {
+    unsafe {
+        gl::VertexAttrib4f(VERTEX_COLOR_LOCATION, 1., 1., 1., 1.);
+    }
+}
+
+/// Creates a default maru program.
+/// Multiplies by vertex colors, meshes without them are drawn as if they were white.
 pub fn default_program(v_effect: Option<&str>,
                        f_effect: Option<&str>
     ) -> Result<Program, String> {
@@ -113,7 +151,36 @@ pub fn default_spritebatch_program(v_effect: Option<&str>,
     Program::new(&[vert, frag])
 }
 
+/// Same as `default_program()`, which reads vertex colors.
+pub fn default_shape_program(v_effect: Option<&str>,
+                             f_effect: Option<&str>
+    ) -> Result<Program, String> {
+    default_program(v_effect, f_effect)
+}
+
+/// Creates a default maru program that draws signed distance field shapes,
+///   for use with the shape drawer.
+pub fn default_sdf_program(v_effect: Option<&str>,
+                           f_effect: Option<&str>
+    ) -> Result<Program, String> {
+    default_program(v_effect.or(Some(shaders::DEFAULT_SDF_VERT)),
+                    f_effect.or(Some(shaders::DEFAULT_SDF_FRAG)))
+}
+
 pub fn debug_texture() -> Texture {
     let img = image::load_from_memory(images::MAHOU).unwrap().to_rgba();
     Texture::new(&img)
 }
+
+/// Helpers shared by the tests of the shape modules.
+#[cfg(test)]
+mod test_util {
+    use nalgebra_glm as glm;
+
+    /// Total area of a list of triangles.
+    pub fn area(tris: &[glm::Vec2]) -> f32 {
+        tris.chunks(3)
+            .map(| t | ((t[1].x - t[0].x) * (t[2].y - t[0].y) - (t[1].y - t[0].y) * (t[2].x - t[0].x)).abs() / 2.)
+            .sum()
+    }
+}
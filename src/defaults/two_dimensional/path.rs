@@ -0,0 +1,478 @@
+use std::f32::consts;
+
+use nalgebra_glm as glm;
+
+use super::{
+    fill_rings,
+    stroke_polyline,
+    FillRule,
+    StrokeStyle,
+};
+
+//
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathCommand {
+    MoveTo(glm::Vec2),
+    LineTo(glm::Vec2),
+    /// Control point, end point.
+    QuadTo(glm::Vec2, glm::Vec2),
+    /// Control points, end point.
+    CubicTo(glm::Vec2, glm::Vec2, glm::Vec2),
+    Close,
+}
+
+/// A flattened part of a path, started by a `move_to()`.
+#[derive(Clone, Debug)]
+pub struct SubPath {
+    pub points: Vec<glm::Vec2>,
+    pub closed: bool,
+}
+
+/// Vector path made of lines and bézier curves.
+///
+/// Arcs are converted to cubic béziers when added,
+///   so any affine transform can be applied exactly.
+#[derive(Clone, Debug, Default)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+    start: glm::Vec2,
+    current: glm::Vec2,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn commands(&self) -> &[PathCommand] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// The end point of the last command.
+    pub fn current_point(&self) -> glm::Vec2 {
+        self.current
+    }
+
+    /// Starts a new sub path.
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        let p = glm::vec2(x, y);
+        self.commands.push(PathCommand::MoveTo(p));
+        self.start = p;
+        self.current = p;
+        self
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        let p = glm::vec2(x, y);
+        self.commands.push(PathCommand::LineTo(p));
+        self.current = p;
+        self
+    }
+
+    /// Quadratic bézier.
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) -> &mut Self {
+        let p = glm::vec2(x, y);
+        self.commands.push(PathCommand::QuadTo(glm::vec2(cx, cy), p));
+        self.current = p;
+        self
+    }
+
+    /// Cubic bézier.
+    pub fn cubic_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) -> &mut Self {
+        let p = glm::vec2(x, y);
+        self.commands.push(PathCommand::CubicTo(glm::vec2(c1x, c1y), glm::vec2(c2x, c2y), p));
+        self.current = p;
+        self
+    }
+
+    /// Elliptical arc from the current point to `x` `y`, like SVG's `A` command.
+    /// `x_rotation` is in radians.
+    /// Of the four arcs that fit, `large_arc` picks the one over 180 degrees,
+    ///   `sweep` picks the one going clockwise on screen.
+    #[allow(clippy::too_many_arguments)]
+    pub fn arc_to(&mut self,
+                  rx: f32,
+                  ry: f32,
+                  x_rotation: f32,
+                  large_arc: bool,
+                  sweep: bool,
+                  x: f32,
+                  y: f32
+    ) -> &mut Self {
+        let from = self.current;
+        let to = glm::vec2(x, y);
+        if from == to {
+            return self;
+        }
+
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0. || ry == 0. {
+            return self.line_to(x, y);
+        }
+
+        // endpoint to center parameterization, from the SVG spec
+        let (sin, cos) = x_rotation.sin_cos();
+        let half = (from - to) / 2.;
+        let p = glm::vec2(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+
+        let lambda = (p.x * p.x) / (rx * rx) + (p.y * p.y) / (ry * ry);
+        if lambda > 1. {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let num = rx * rx * ry * ry - rx * rx * p.y * p.y - ry * ry * p.x * p.x;
+        let den = rx * rx * p.y * p.y + ry * ry * p.x * p.x;
+        let mut coef = (num / den).max(0.).sqrt();
+        if large_arc == sweep {
+            coef = -coef;
+        }
+        let c = glm::vec2(coef * rx * p.y / ry, -coef * ry * p.x / rx);
+        let mid = (from + to) / 2.;
+        let center = glm::vec2(cos * c.x - sin * c.y, sin * c.x + cos * c.y) + mid;
+
+        let angle = | v: glm::Vec2 | v.y.atan2(v.x);
+        let start = angle(glm::vec2((p.x - c.x) / rx, (p.y - c.y) / ry));
+        let mut delta = angle(glm::vec2((-p.x - c.x) / rx, (-p.y - c.y) / ry)) - start;
+        if sweep && delta < 0. {
+            delta += consts::PI * 2.;
+        } else if !sweep && delta > 0. {
+            delta -= consts::PI * 2.;
+        }
+
+        self.ellipse_arc(center, rx, ry, x_rotation, start, delta);
+        // avoid drifting from the exact end point
+        self.current = to;
+        if let Some(PathCommand::CubicTo(_, _, end)) = self.commands.last_mut() {
+            *end = to;
+        }
+        self
+    }
+
+    /// Arc around a center, angles in radians, positive `sweep` goes clockwise on screen.
+    /// Connects to the current point with a line, or starts a new sub path if the path is empty.
+    pub fn arc(&mut self, x: f32, y: f32, r: f32, start: f32, sweep: f32) -> &mut Self {
+        let center = glm::vec2(x, y);
+        let first = center + glm::vec2(start.cos(), start.sin()) * r;
+        if self.commands.is_empty() {
+            self.move_to(first.x, first.y);
+        } else {
+            self.line_to(first.x, first.y);
+        }
+        self.ellipse_arc(center, r, r, 0., start, sweep);
+        self
+    }
+
+    /// Adds cubics approximating an elliptical arc, at most a quarter turn each.
+    fn ellipse_arc(&mut self, center: glm::Vec2, rx: f32, ry: f32, rotation: f32, start: f32, sweep: f32) {
+        let (sin, cos) = rotation.sin_cos();
+        let point = | at: f32 | {
+            let v = glm::vec2(at.cos() * rx, at.sin() * ry);
+            center + glm::vec2(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+        };
+        let tangent = | at: f32 | {
+            let v = glm::vec2(-at.sin() * rx, at.cos() * ry);
+            glm::vec2(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+        };
+
+        let count = (sweep.abs() / (consts::PI / 2.)).ceil().max(1.) as usize;
+        let step = sweep / count as f32;
+        let k = 4. / 3. * (step / 4.).tan();
+        for i in 0..count {
+            let a0 = start + step * i as f32;
+            let a1 = a0 + step;
+            let p0 = point(a0);
+            let p1 = point(a1);
+            let c1 = p0 + tangent(a0) * k;
+            let c2 = p1 - tangent(a1) * k;
+            self.cubic_to(c1.x, c1.y, c2.x, c2.y, p1.x, p1.y);
+        }
+    }
+
+    /// Closes the current sub path back to its start.
+    pub fn close(&mut self) -> &mut Self {
+        self.commands.push(PathCommand::Close);
+        self.current = self.start;
+        self
+    }
+
+    /// Applies an affine transform to every point.
+    pub fn transform(&mut self, m: &glm::Mat3) {
+        let apply = | p: &mut glm::Vec2 | {
+            let v = m * glm::vec3(p.x, p.y, 1.);
+            *p = glm::vec2(v.x, v.y);
+        };
+        for command in self.commands.iter_mut() {
+            match command {
+                PathCommand::MoveTo(p) | PathCommand::LineTo(p) => apply(p),
+                PathCommand::QuadTo(c, p) => {
+                    apply(c);
+                    apply(p);
+                }
+                PathCommand::CubicTo(c1, c2, p) => {
+                    apply(c1);
+                    apply(c2);
+                    apply(p);
+                }
+                PathCommand::Close => {}
+            }
+        }
+        apply(&mut self.start);
+        apply(&mut self.current);
+    }
+
+    /// Splits curves into lines no further than `tolerance` from the curve.
+    pub fn flatten(&self, tolerance: f32) -> Vec<SubPath> {
+        let tolerance = tolerance.max(0.0001);
+        let mut ret = Vec::new();
+        let mut current = SubPath {
+            points: Vec::new(),
+            closed: false,
+        };
+        let mut last = glm::vec2(0., 0.);
+
+        let finish = | current: &mut SubPath, ret: &mut Vec<SubPath>, closed: bool | {
+            let start = current.points.first().copied();
+            if current.points.len() > 1 {
+                current.closed = closed;
+                ret.push(current.clone());
+            }
+            current.points.clear();
+            current.closed = false;
+            start
+        };
+
+        for command in self.commands.iter() {
+            match *command {
+                PathCommand::MoveTo(p) => {
+                    finish(&mut current, &mut ret, false);
+                    current.points.push(p);
+                    last = p;
+                }
+                PathCommand::LineTo(p) => {
+                    if current.points.is_empty() {
+                        current.points.push(last);
+                    }
+                    current.points.push(p);
+                    last = p;
+                }
+                PathCommand::QuadTo(c, p) => {
+                    if current.points.is_empty() {
+                        current.points.push(last);
+                    }
+                    let dd = (last - c * 2. + p).norm();
+                    let count = segment_count(dd, 2. / 8., tolerance);
+                    for i in 1..=count {
+                        let t = i as f32 / count as f32;
+                        let mt = 1. - t;
+                        current.points.push(last * (mt * mt) + c * (2. * mt * t) + p * (t * t));
+                    }
+                    last = p;
+                }
+                PathCommand::CubicTo(c1, c2, p) => {
+                    if current.points.is_empty() {
+                        current.points.push(last);
+                    }
+                    let dd = (last - c1 * 2. + c2).norm().max((c1 - c2 * 2. + p).norm());
+                    let count = segment_count(dd, 6. / 8., tolerance);
+                    for i in 1..=count {
+                        let t = i as f32 / count as f32;
+                        let mt = 1. - t;
+                        current.points.push(last * (mt * mt * mt)
+                                            + c1 * (3. * mt * mt * t)
+                                            + c2 * (3. * mt * t * t)
+                                            + p * (t * t * t));
+                    }
+                    last = p;
+                }
+                PathCommand::Close => {
+                    if let Some(start) = finish(&mut current, &mut ret, true) {
+                        last = start;
+                    }
+                }
+            }
+        }
+        finish(&mut current, &mut ret, false);
+
+        ret
+    }
+
+    /// Triangles filling the path, appended to `out`.
+    /// Every sub path is treated as closed.
+    /// Sub paths can be nested and can cross each other or themselves, see `fill_rings()`.
+    pub fn fill(&self, rule: FillRule, tolerance: f32, out: &mut Vec<glm::Vec2>) {
+        let rings: Vec<_> = self.flatten(tolerance)
+                                .into_iter()
+                                .map(| sub | sub.points)
+                                .collect();
+        fill_rings(&rings, rule, out);
+    }
+
+    /// Triangles of a stroke along the path, appended to `out`.
+    pub fn stroke(&self, style: &StrokeStyle, tolerance: f32, out: &mut Vec<glm::Vec2>) {
+        for sub in self.flatten(tolerance) {
+            stroke_polyline(&sub.points, sub.closed, style, tolerance, out);
+        }
+    }
+}
+
+/// Segments needed for a bézier to stay within `tolerance`, with Wang's formula.
+/// `dd` is the largest second difference of the control points,
+///   `factor` is degree * (degree - 1) / 8.
+fn segment_count(dd: f32, factor: f32, tolerance: f32) -> usize {
+    ((factor * dd / tolerance).sqrt().ceil() as usize).clamp(1, 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::area;
+
+    fn distance_to_polyline(p: glm::Vec2, points: &[glm::Vec2]) -> f32 {
+        points.windows(2)
+              .map(| pair | {
+                  let (a, b) = (pair[0], pair[1]);
+                  let ab = b - a;
+                  let t = (glm::dot(&(p - a), &ab) / ab.norm_squared().max(1e-12)).clamp(0., 1.);
+                  glm::distance(&p, &(a + ab * t))
+              })
+              .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn segment_count_wang() {
+        // straight curves only need one segment
+        assert_eq!(segment_count(0., 6. / 8., 0.25), 1);
+        // sqrt(0.75 * 100 / 0.25) = sqrt(300) = 17.3
+        assert_eq!(segment_count(100., 6. / 8., 0.25), 18);
+        assert_eq!(segment_count(f32::MAX, 6. / 8., 0.0001), 1024);
+    }
+
+    #[test]
+    fn flatten_stays_within_tolerance() {
+        let tolerance = 0.1;
+        let mut path = Path::new();
+        path.move_to(0., 0.)
+            .quad_to(50., 100., 100., 0.)
+            .cubic_to(150., -100., 50., -100., 200., 50.);
+        let subs = path.flatten(tolerance);
+        assert_eq!(subs.len(), 1);
+        let points = &subs[0].points;
+
+        let quad = | t: f32 | {
+            let mt = 1. - t;
+            glm::vec2(0., 0.) * (mt * mt) + glm::vec2(50., 100.) * (2. * mt * t) + glm::vec2(100., 0.) * (t * t)
+        };
+        let cubic = | t: f32 | {
+            let mt = 1. - t;
+            glm::vec2(100., 0.) * (mt * mt * mt)
+                + glm::vec2(150., -100.) * (3. * mt * mt * t)
+                + glm::vec2(50., -100.) * (3. * mt * t * t)
+                + glm::vec2(200., 50.) * (t * t * t)
+        };
+        for i in 0..=1000 {
+            let t = i as f32 / 1000.;
+            assert!(distance_to_polyline(quad(t), points) <= tolerance * 1.01);
+            assert!(distance_to_polyline(cubic(t), points) <= tolerance * 1.01);
+        }
+
+        // tighter tolerance, more points
+        assert!(path.flatten(tolerance / 10.)[0].points.len() > points.len());
+    }
+
+    #[test]
+    fn flatten_sub_paths() {
+        let mut path = Path::new();
+        path.move_to(0., 0.).line_to(1., 0.).line_to(1., 1.).close()
+            .line_to(0., 1.)
+            .move_to(5., 5.)
+            .move_to(6., 6.).line_to(7., 7.);
+        let subs = path.flatten(0.1);
+        assert_eq!(subs.len(), 3);
+        assert!(subs[0].closed);
+        // a line after close starts from the start of the closed sub path
+        assert_eq!(subs[1].points, vec![glm::vec2(0., 0.), glm::vec2(0., 1.)]);
+        assert!(!subs[1].closed);
+        // the lone move_to is dropped
+        assert_eq!(subs[2].points[0], glm::vec2(6., 6.));
+    }
+
+    #[test]
+    fn arc_to_quarter_circle() {
+        let tolerance = 0.01;
+        let mut path = Path::new();
+        path.move_to(10., 0.).arc_to(10., 10., 0., false, true, 0., 10.);
+        assert_eq!(path.current_point(), glm::vec2(0., 10.));
+
+        let points = &path.flatten(tolerance)[0].points;
+        assert_eq!(points[points.len() - 1], glm::vec2(0., 10.));
+        for p in points {
+            // clockwise on screen from (10, 0) to (0, 10) is around the origin
+            assert!((p.norm() - 10.).abs() < 0.02, "{:?}", p);
+            assert!(p.x >= -0.01 && p.y >= -0.01);
+        }
+    }
+
+    #[test]
+    fn arc_to_flags_pick_the_arc() {
+        let flatten = | large_arc, sweep | {
+            let mut path = Path::new();
+            path.move_to(10., 0.).arc_to(10., 10., 0., large_arc, sweep, 0., 10.);
+            path.flatten(0.01).remove(0).points
+        };
+
+        // small arc around (10, 10) the other way
+        for p in flatten(false, false).iter() {
+            assert!((glm::distance(p, &glm::vec2(10., 10.)) - 10.).abs() < 0.02);
+            assert!(p.x <= 10.01 && p.y <= 10.01);
+        }
+        // large arcs go three quarters of the way around
+        let large = flatten(true, true);
+        assert!(large.iter().any(| p | p.x > 19.9));
+        let large = flatten(true, false);
+        assert!(large.iter().any(| p | p.x < -9.9));
+    }
+
+    #[test]
+    fn arc_to_scales_up_small_radii() {
+        let mut path = Path::new();
+        path.move_to(0., 0.).arc_to(1., 1., 0., false, true, 10., 0.);
+        for p in path.flatten(0.01)[0].points.iter() {
+            assert!((glm::distance(p, &glm::vec2(5., 0.)) - 5.).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn arc_to_degenerate() {
+        let mut path = Path::new();
+        path.move_to(1., 1.).arc_to(5., 5., 0., false, true, 1., 1.);
+        assert_eq!(path.commands().len(), 1);
+
+        path.arc_to(0., 5., 0., false, true, 4., 1.);
+        assert_eq!(path.commands()[1], PathCommand::LineTo(glm::vec2(4., 1.)));
+    }
+
+    #[test]
+    fn full_circle_area() {
+        let mut path = Path::new();
+        path.arc(0., 0., 10., 0., consts::PI * 2.).close();
+        let mut out = Vec::new();
+        path.fill(FillRule::NonZero, 0.01, &mut out);
+        assert!((area(&out) - consts::PI * 100.).abs() < 0.5);
+    }
+
+    #[test]
+    fn transform_keeps_arcs_exact() {
+        let mut path = Path::new();
+        path.arc(0., 0., 1., 0., consts::PI * 2.);
+        path.transform(&glm::scaling2d(&glm::vec2(10., 10.)));
+        for p in path.flatten(0.01)[0].points.iter() {
+            assert!((p.norm() - 10.).abs() < 0.02);
+        }
+    }
+}
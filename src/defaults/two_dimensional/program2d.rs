@@ -35,6 +35,14 @@ impl Program2d {
         Self::from(super::default_spritebatch_program(None, None).unwrap())
     }
 
+    pub fn default_shape_program() -> Self {
+        Self::from(super::default_shape_program(None, None).unwrap())
+    }
+
+    pub fn default_sdf_program() -> Self {
+        Self::from(super::default_sdf_program(None, None).unwrap())
+    }
+
     pub fn get_location(&self, name: &str) -> Location {
         Location::new(&self.prog, name)
     }
@@ -1,3 +1,5 @@
+use std::f32::consts;
+
 use image::{
     RgbaImage,
     Rgba,
@@ -6,105 +8,544 @@ use nalgebra_glm as glm;
 
 use crate::{
     gfx::{
+        FrameStats,
+        RingBuffer,
         Texture,
+        UvRegion,
+        Vertex,
+        VertexArray,
     },
     math::{
-        Transform2d,
+        Color,
     },
 };
 
 use super::{
-    Mesh2d,
-    Vertex2d,
+    ellipse_points,
+    fill_convex,
+    fill_polygon,
+    rounded_rect_points,
+    stroke_polyline,
+    FillRule,
+    LineCap,
     Locations,
+    Path,
+    SdfVertex,
+    ColoredVertex2d,
+    StrokeAlign,
+    StrokeStyle,
+    Svg,
+    DEFAULT_TOLERANCE,
 };
 
 //
 
-// polygon
-// line
-// triangle
+/// Border and shadow of signed distance field shapes.
+/// Widths, offsets and blur are in shape units, not pixels,
+///   so they scale along with the shape under the coordinate transform.
+#[derive(Copy, Clone, Debug)]
+pub struct SdfStyle {
+    /// Drawn inside the shape's edge.
+    pub border_width: f32,
+    pub border_color: Color,
+    pub shadow_offset: glm::Vec2,
+    pub shadow_blur: f32,
+    /// Transparent for no shadow.
+    pub shadow_color: Color,
+}
+
+impl Default for SdfStyle {
+    fn default() -> Self {
+        Self {
+            border_width: 0.,
+            border_color: Color::black(),
+            shadow_offset: glm::vec2(0., 0.),
+            shadow_blur: 0.,
+            shadow_color: Color::new_rgba(0., 0., 0., 0.),
+        }
+    }
+}
+
+//
+
+/// Triangles streamed through a `RingBuffer`.
+/// Shape batches change completely every flush,
+///   so this avoids reallocating or stalling on a buffer the GPU may still be reading.
+struct TriangleStream<V: Vertex> {
+    vertices: Vec<V>,
+    ring: RingBuffer<V>,
+    vao: VertexArray,
+}
+
+impl<V: Vertex> TriangleStream<V> {
+    /// Vertices per ring buffer section, a multiple of 3 so triangles are never split.
+    const SECTION_LEN: usize = 3 * 4096;
+    const SECTION_COUNT: usize = 3;
+
+    fn new() -> Self {
+        let ring = RingBuffer::new(Self::SECTION_LEN, Self::SECTION_COUNT);
+        let mut vao = VertexArray::new();
+        vao.bind();
+        ring.buffer().bind_to(gl::ARRAY_BUFFER);
+        V::set_attributes(&mut vao).unwrap();
+        vao.unbind();
+
+        Self {
+            vertices: Vec::new(),
+            ring,
+            vao,
+        }
+    }
+
+    /// Uploads and draws everything, in as many draw calls as needed to fit the ring buffer.
+    fn flush(&mut self) {
+        for chunk in self.vertices.chunks(Self::SECTION_LEN) {
+            let offset = self.ring.push_slice(chunk);
+            self.vao.bind();
+            unsafe {
+                gl::DrawArrays(gl::TRIANGLES, offset as _, chunk.len() as _);
+            }
+            self.vao.unbind();
+            FrameStats::record_draw(chunk.len(), 1);
+        }
+        self.vertices.clear();
+    }
+}
 
-// lined rect
+//
 
+/// Batches shapes into one stream of triangles, drawn with `flush()`.
+///
+/// Shapes are colored per vertex with the current color,
+///   and the bound texture is stretched over each shape's bounding box,
+///   or over the part of it set with `set_uv_region()`.
+/// For plain colored shapes, draw with `white_texture()` bound.
+/// Needs a program that reads vertex colors, like `Program2d::default_program()`.
+///
+/// Curves are split into as many segments as needed to look smooth at their size on screen.
+///
+/// Rects, rounded rects, circles, capsules and lines can also be drawn as
+///   signed distance fields, one quad each, with anti-aliasing, borders and shadows.
+/// These go in a separate batch drawn with `flush_sdf()`,
+///   and need a program like `Program2d::default_sdf_program()`.
 pub struct ShapeDrawer {
-    line_thickness: f32,
+    stroke: StrokeStyle,
+    color: Color,
+    tolerance: f32,
+    scale: f32,
+    sdf_style: SdfStyle,
+    uv_region: UvRegion,
 
-    mesh_quad: Mesh2d,
-    mesh_circle: Mesh2d,
+    mesh: TriangleStream<ColoredVertex2d>,
+    sdf_mesh: TriangleStream<SdfVertex>,
+    // triangles of the shape being generated
+    scratch: Vec<glm::Vec2>,
     tex_white: Texture,
 }
 
+impl Default for ShapeDrawer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ShapeDrawer {
-    pub fn new(circle_resolution: usize) -> Self {
+    pub fn new() -> Self {
         let white = RgbaImage::from_pixel(1, 1, Rgba::from([255, 255, 255, 255]));
+
         Self {
-            mesh_quad: Mesh2d::new(Vertex2d::quad(false),
-                                   Vec::new(),
-                                   gl::STATIC_DRAW,
-                                   gl::TRIANGLE_STRIP),
-            mesh_circle: Mesh2d::new(Vertex2d::circle(circle_resolution),
-                                     Vec::new(),
-                                     gl::STATIC_DRAW,
-                                     gl::TRIANGLE_FAN),
+            stroke: StrokeStyle::default(),
+            color: Color::white(),
+            tolerance: DEFAULT_TOLERANCE,
+            scale: 1.,
+            sdf_style: SdfStyle::default(),
+            uv_region: UvRegion::new(0., 0., 1., 1.),
+            mesh: TriangleStream::new(),
+            sdf_mesh: TriangleStream::new(),
+            scratch: Vec::new(),
             tex_white: Texture::new(&white),
-            line_thickness: 2.0,
         }
     }
 
-    // TODO clean up api in general
-    pub fn draw_quad(&self) {
-        self.mesh_quad.draw();
+    pub fn white_texture(&self) -> &Texture {
+        &self.tex_white
     }
 
     pub fn line_thickness_mut(&mut self) -> &mut f32 {
-        &mut self.line_thickness
+        &mut self.stroke.thickness
     }
 
-    /// Sets locations.diffuse() and locations.model()
-    pub fn filled_rectangle(&self, locations: &Locations, x1: f32, y1: f32, x2: f32, y2: f32) {
-        let temp = Transform2d {
-            position: glm::vec2(x1, y1),
-            scale:    glm::vec2(x2 - x1, y2 - y1),
-            .. Transform2d::identity()
-        };
-        locations.set_sprite_px(&self.tex_white, &temp);
-        self.mesh_quad.draw();
+    pub fn stroke_style(&self) -> &StrokeStyle {
+        &self.stroke
+    }
+
+    pub fn stroke_style_mut(&mut self) -> &mut StrokeStyle {
+        &mut self.stroke
+    }
+
+    /// Color of shapes added after this.
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
     }
 
-    /// Sets locations.diffuse() and locations.model()
-    pub fn circle(&self, locations: &Locations, x: f32, y: f32, r: f32) {
-        let temp = Transform2d {
+    /// Part of the bound texture stretched over shapes added after this.
+    pub fn set_uv_region(&mut self, region: UvRegion) {
+        self.uv_region = region;
+    }
+
+    pub fn uv_region(&self) -> &UvRegion {
+        &self.uv_region
+    }
+
+    /// Max distance in pixels on screen that curves are allowed to stray from the true curve.
+    /// Lower is smoother, but uses more vertices.
+    pub fn set_tolerance(&mut self, tolerance: f32) {
+        self.tolerance = tolerance;
+    }
+
+    pub fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    /// How many pixels on screen one unit of shape coordinates covers.
+    /// Set automatically by `BoundShapeDrawer` from the coordinate stack.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Tolerance in shape coordinates.
+    fn local_tolerance(&self) -> f32 {
+        if self.scale > 0. {
+            self.tolerance / self.scale
+        } else {
+            self.tolerance
+        }
+    }
+
+    /// Number of vertices waiting to be drawn.
+    pub fn vertex_count(&self) -> usize {
+        self.mesh.vertices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mesh.vertices.is_empty()
+    }
+
+    /// Draws everything batched so far, in one draw call unless it is very large.
+    /// Uses the bound texture.
+    /// Sets locations.model()
+    pub fn flush(&mut self, locations: &Locations) {
+        if self.is_empty() {
+            return;
+        }
+        locations.model().set(&glm::Mat3::identity());
+        self.mesh.flush();
+    }
+
+    /// Discards everything batched so far, including sdf shapes.
+    pub fn clear(&mut self) {
+        self.mesh.vertices.clear();
+        self.sdf_mesh.vertices.clear();
+    }
+
+    /// Moves the triangles in `scratch` to the batch.
+    fn push_scratch(&mut self) {
+        if self.scratch.is_empty() {
+            return;
+        }
+
+        let (min, max) = self.scratch.iter().fold(
+            (glm::vec2(f32::INFINITY, f32::INFINITY), glm::vec2(f32::NEG_INFINITY, f32::NEG_INFINITY)),
+            | (min, max), p | (glm::min2(&min, p), glm::max2(&max, p)));
+        let size = max - min;
+        let region = self.uv_region;
+        let inv_w = if size.x > 0. { region.width() / size.x } else { 0. };
+        let inv_h = if size.y > 0. { region.height() / size.y } else { 0. };
+
+        let color = self.color;
+        self.mesh.vertices.extend(self.scratch.iter().map(| p | ColoredVertex2d {
+            position: *p,
+            uv:       region.c1 + glm::vec2((p.x - min.x) * inv_w, (p.y - min.y) * inv_h),
+            color,
+        }));
+        self.scratch.clear();
+    }
+
+    // fills
+
+    pub fn filled_rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let points = [
+            glm::vec2(x1, y1),
+            glm::vec2(x2, y1),
+            glm::vec2(x2, y2),
+            glm::vec2(x1, y2),
+        ];
+        fill_convex(&points, &mut self.scratch);
+        self.push_scratch();
+    }
+
+    /// Colors go top left, top right, bottom right, bottom left on screen,
+    ///   and are multiplied by the current color.
+    /// note: blends across the diagonal, use `GradientTexture::corners()` for a smooth blend
+    pub fn filled_rectangle_corners(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, colors: &[Color; 4]) {
+        let tint = self.color;
+        let region = self.uv_region;
+        let corner = | x: f32, y: f32, u: f32, v: f32, color: Color | ColoredVertex2d {
             position: glm::vec2(x, y),
-            scale:    glm::vec2(r, r),
-            .. Transform2d::identity()
+            uv:       glm::vec2(u, v),
+            color:    Color::new_rgba(color.r * tint.r,
+                                      color.g * tint.g,
+                                      color.b * tint.b,
+                                      color.a * tint.a),
         };
-        locations.set_sprite_px(&self.tex_white, &temp);
-        self.mesh_circle.draw();
+        let a = corner(x1, y1, region.c1.x, region.c1.y, colors[0]);
+        let b = corner(x2, y1, region.c2.x, region.c1.y, colors[1]);
+        let c = corner(x2, y2, region.c2.x, region.c2.y, colors[2]);
+        let d = corner(x1, y2, region.c1.x, region.c2.y, colors[3]);
+        self.mesh.vertices.extend([a, b, c, a, c, d].iter().copied());
     }
 
-    /// Sets locations.diffuse() and locations.model()
-    pub fn horizontal_line(&self, locations: &Locations, y: f32, x1: f32, x2: f32) {
-        let y1 = y - self.line_thickness / 2.;
-        let y2 = y + self.line_thickness / 2.;
-        self.filled_rectangle(locations, x1, y1, x2, y2);
+    pub fn circle(&mut self, x: f32, y: f32, r: f32) {
+        self.ellipse(x, y, r, r);
     }
 
-    /// Sets locations.diffuse() and locations.model()
-    pub fn vertical_line(&self, locations: &Locations, x: f32, y1: f32, y2: f32) {
-        let x1 = x - self.line_thickness / 2.;
-        let x2 = x + self.line_thickness / 2.;
-        self.filled_rectangle(locations, x1, y1, x2, y2);
+    pub fn ellipse(&mut self, x: f32, y: f32, rx: f32, ry: f32) {
+        let mut points = Vec::new();
+        ellipse_points(glm::vec2(x, y), rx, ry, 0., consts::PI * 2., self.local_tolerance(), &mut points);
+        fill_convex(&points, &mut self.scratch);
+        self.push_scratch();
     }
 
-    /// Sets locations.diffuse() and locations.model()
-    pub fn line(&self, locations: &Locations, x1: f32, y1: f32, x2: f32, y2: f32) {
-        if x1 == x2 {
-            self.vertical_line(locations, x1, y1, y2);
-        } else if y1 == y2 {
-            self.horizontal_line(locations, y1, x1, x2);
-        } else {
-            // TODO
+    pub fn triangle(&mut self, a: glm::Vec2, b: glm::Vec2, c: glm::Vec2) {
+        self.scratch.extend_from_slice(&[a, b, c]);
+        self.push_scratch();
+    }
+
+    /// Fills a convex or concave polygon.
+    pub fn polygon(&mut self, points: &[glm::Vec2]) {
+        fill_polygon(points, &[], &mut self.scratch);
+        self.push_scratch();
+    }
+
+    /// Fills a polygon, leaving out the holes.
+    pub fn polygon_with_holes(&mut self, points: &[glm::Vec2], holes: &[&[glm::Vec2]]) {
+        fill_polygon(points, holes, &mut self.scratch);
+        self.push_scratch();
+    }
+
+    pub fn fill_path(&mut self, path: &Path, rule: FillRule) {
+        path.fill(rule, self.local_tolerance(), &mut self.scratch);
+        self.push_scratch();
+    }
+
+    /// Draws every shape of the svg with its own colors, multiplied by the current color.
+    /// uvs span the svg's width and height.
+    pub fn svg(&mut self, svg: &Svg) {
+        let tolerance = self.local_tolerance();
+        svg.tessellate(tolerance, self.color, &mut self.mesh.vertices);
+    }
+
+    pub fn rounded_rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        let mut points = Vec::new();
+        rounded_rect_points(x1, y1, x2, y2, radius, self.local_tolerance(), &mut points);
+        fill_convex(&points, &mut self.scratch);
+        self.push_scratch();
+    }
+
+    // lines
+    //   drawn with the current stroke style
+
+    pub fn horizontal_line(&mut self, y: f32, x1: f32, x2: f32) {
+        self.line(x1, y, x2, y);
+    }
+
+    pub fn vertical_line(&mut self, x: f32, y1: f32, y2: f32) {
+        self.line(x, y1, x, y2);
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.polyline(&[glm::vec2(x1, y1), glm::vec2(x2, y2)]);
+    }
+
+    pub fn polyline(&mut self, points: &[glm::Vec2]) {
+        let tolerance = self.local_tolerance();
+        stroke_polyline(points, false, &self.stroke, tolerance, &mut self.scratch);
+        self.push_scratch();
+    }
+
+    /// Outline of a closed polygon, the last point is connected to the first.
+    pub fn polygon_outline(&mut self, points: &[glm::Vec2]) {
+        let tolerance = self.local_tolerance();
+        stroke_polyline(points, true, &self.stroke, tolerance, &mut self.scratch);
+        self.push_scratch();
+    }
+
+    /// Strokes every sub path, closed sub paths are joined at their start.
+    pub fn stroke_path(&mut self, path: &Path) {
+        let tolerance = self.local_tolerance();
+        path.stroke(&self.stroke, tolerance, &mut self.scratch);
+        self.push_scratch();
+    }
+
+    // outlines
+    //   closed outlines are aligned with the stroke style's `align`
+
+    pub fn rectangle_outline(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let points = [
+            glm::vec2(x1, y1),
+            glm::vec2(x2, y1),
+            glm::vec2(x2, y2),
+            glm::vec2(x1, y2),
+        ];
+        self.polygon_outline(&points);
+    }
+
+    pub fn rounded_rectangle_outline(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        let mut points = Vec::new();
+        rounded_rect_points(x1, y1, x2, y2, radius, self.local_tolerance(), &mut points);
+        self.polygon_outline(&points);
+    }
+
+    pub fn circle_outline(&mut self, x: f32, y: f32, r: f32) {
+        self.ellipse_outline(x, y, r, r);
+    }
+
+    pub fn ellipse_outline(&mut self, x: f32, y: f32, rx: f32, ry: f32) {
+        let mut points = Vec::new();
+        ellipse_points(glm::vec2(x, y), rx, ry, 0., consts::PI * 2., self.local_tolerance(), &mut points);
+        self.polygon_outline(&points);
+    }
+
+    /// Open arc, drawn with the stroke style's caps.
+    /// Angles are in radians, positive `sweep` goes clockwise on screen.
+    pub fn arc(&mut self, x: f32, y: f32, r: f32, start: f32, sweep: f32) {
+        let r = match self.stroke.align {
+            StrokeAlign::Inner  => r - self.stroke.thickness / 2.,
+            StrokeAlign::Center => r,
+            StrokeAlign::Outer  => r + self.stroke.thickness / 2.,
+        };
+        let mut points = Vec::new();
+        ellipse_points(glm::vec2(x, y), r, r, start, sweep, self.local_tolerance(), &mut points);
+        self.polyline(&points);
+    }
+
+    /// Outline of a pie slice, an arc closed through its center.
+    /// Angles are in radians, positive `sweep` goes clockwise on screen.
+    pub fn pie_outline(&mut self, x: f32, y: f32, r: f32, start: f32, sweep: f32) {
+        let center = glm::vec2(x, y);
+        let mut points = vec![center];
+        ellipse_points(center, r, r, start, sweep, self.local_tolerance(), &mut points);
+        self.polygon_outline(&points);
+    }
+
+    // signed distance field shapes
+    //   drawn with the current color and sdf style
+
+    pub fn sdf_style(&self) -> &SdfStyle {
+        &self.sdf_style
+    }
+
+    pub fn sdf_style_mut(&mut self) -> &mut SdfStyle {
+        &mut self.sdf_style
+    }
+
+    pub fn sdf_is_empty(&self) -> bool {
+        self.sdf_mesh.vertices.is_empty()
+    }
+
+    /// Draws every sdf shape batched so far, in one draw call unless it is very large.
+    /// Sets locations.model()
+    pub fn flush_sdf(&mut self, locations: &Locations) {
+        if self.sdf_is_empty() {
+            return;
         }
+        locations.model().set(&glm::Mat3::identity());
+        self.sdf_mesh.flush();
+    }
+
+    pub fn sdf_rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.sdf_rounded_rectangle(x1, y1, x2, y2, 0.);
+    }
+
+    pub fn sdf_rounded_rectangle(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        let center = glm::vec2(x1 + x2, y1 + y2) / 2.;
+        let half = glm::vec2((x2 - x1).abs(), (y2 - y1).abs()) / 2.;
+        self.push_sdf_box(center, glm::vec2(1., 0.), half, radius);
+    }
+
+    pub fn sdf_circle(&mut self, x: f32, y: f32, r: f32) {
+        self.push_sdf_box(glm::vec2(x, y), glm::vec2(1., 0.), glm::vec2(r, r), r);
+    }
+
+    /// A line segment with round ends of radius `r`.
+    pub fn sdf_capsule(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, r: f32) {
+        self.push_sdf_segment(glm::vec2(x1, y1), glm::vec2(x2, y2), r, r, r);
+    }
+
+    /// Drawn with the current stroke style's thickness and cap.
+    pub fn sdf_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let hw = self.stroke.thickness / 2.;
+        let (ext, radius) = match self.stroke.cap {
+            LineCap::Butt   => (0., 0.),
+            LineCap::Square => (hw, 0.),
+            LineCap::Round  => (hw, hw),
+        };
+        self.push_sdf_segment(glm::vec2(x1, y1), glm::vec2(x2, y2), hw, ext, radius);
+    }
+
+    /// Box along a segment, extended by `ext` past each end.
+    fn push_sdf_segment(&mut self, a: glm::Vec2, b: glm::Vec2, hw: f32, ext: f32, radius: f32) {
+        let len = glm::distance(&a, &b);
+        let axis = if len > 0. { (b - a) / len } else { glm::vec2(1., 0.) };
+        self.push_sdf_box((a + b) / 2., axis, glm::vec2(len / 2. + ext, hw), radius);
+    }
+
+    /// `axis` is the unit direction of the box's x axis.
+    fn push_sdf_box(&mut self, center: glm::Vec2, axis: glm::Vec2, half: glm::Vec2, radius: f32) {
+        let style = self.sdf_style;
+        let perp = glm::vec2(-axis.y, axis.x);
+        let radius = radius.min(half.x).min(half.y).max(0.);
+        let shadow_offset = glm::vec2(glm::dot(&style.shadow_offset, &axis),
+                                      glm::dot(&style.shadow_offset, &perp));
+
+        // room for a couple pixels of anti-aliasing and the shadow
+        let mut margin = 2. / self.scale.max(0.0001);
+        if style.shadow_color.a > 0. {
+            margin += shadow_offset.x.abs().max(shadow_offset.y.abs()) + style.shadow_blur;
+        }
+        let ext = half + glm::vec2(margin, margin);
+
+        let params = glm::vec4(half.x, half.y, radius, style.border_width);
+        let shadow = glm::vec4(shadow_offset.x, shadow_offset.y, style.shadow_blur, 0.);
+        let color = self.color;
+        let vertex = | sx: f32, sy: f32 | {
+            let local = glm::vec2(ext.x * sx, ext.y * sy);
+            SdfVertex {
+                position: center + axis * local.x + perp * local.y,
+                uv: glm::vec2(sx + 1., sy + 1.) / 2.,
+                color,
+                local,
+                params,
+                border_color: style.border_color,
+                shadow,
+                shadow_color: style.shadow_color,
+            }
+        };
+
+        let corners = [
+            vertex(-1., -1.),
+            vertex( 1., -1.),
+            vertex( 1.,  1.),
+            vertex(-1.,  1.),
+        ];
+        self.sdf_mesh.vertices.extend([0, 1, 2, 0, 2, 3].iter().map(| i | corners[*i]));
     }
 }
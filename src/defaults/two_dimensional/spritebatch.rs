@@ -1,12 +1,12 @@
-use memoffset::offset_of;
+use nalgebra_glm as glm;
 
 use crate::{
     gfx::{
         Instancer,
         BoundInstancer,
-        VertexArray,
-        VertexAttribute,
-        Mesh,
+        MeshId,
+        BufferUsage,
+        Primitive,
         UvRegion,
         Vertex,
     },
@@ -25,12 +25,32 @@ use super::{
 //
 
 // TODO think about using mat3 instead of t2d
-#[derive(Debug)]
+#[derive(Debug, Vertex)]
 #[repr(C)]
+#[divisor = 1]
 pub struct SbSprite {
+    #[location = 2]
     pub uv: UvRegion,
+    #[location = 3]
     pub transform: Transform2d,
+    #[location = 6]
     pub color: Color,
+    /// Colors multiplied with `color`, packed with `pack_color()`,
+    ///   top left, top right, bottom right, bottom left on screen.
+    /// Blended over the mesh by its uvs,
+    ///   across the diagonal for quads.
+    #[location = 13]
+    #[integer]
+    pub corners: glm::UVec4,
+}
+
+impl SbSprite {
+    pub fn set_corner_colors(&mut self, colors: &[Color; 4]) {
+        self.corners = glm::vec4(pack_color(colors[0]),
+                                 pack_color(colors[1]),
+                                 pack_color(colors[2]),
+                                 pack_color(colors[3]));
+    }
 }
 
 impl Default for SbSprite {
@@ -39,88 +59,86 @@ impl Default for SbSprite {
             uv: UvRegion::new(0., 0., 1., 1.),
             transform: Transform2d::identity(),
             color: Color::new_rgba(1., 1., 1., 1.),
+            // white
+            corners: glm::UVec4::repeat(u32::MAX),
         }
     }
 }
 
-impl Vertex for SbSprite {
-    fn set_attributes(vao: &mut VertexArray) {
-        let base = VertexAttribute {
-            size: 4,
-            ty: gl::FLOAT,
-            normalized: false,
-            stride: std::mem::size_of::<Self>(),
-            offset: 0,
-            divisor: 1,
-        };
+/// Packs a color into 8 bits per channel, red in the lowest byte.
+pub fn pack_color(color: Color) -> u32 {
+    let channel = | v: f32 | (v.clamp(0., 1.) * 255.).round() as u32;
+    channel(color.r) |
+        channel(color.g) << 8 |
+        channel(color.b) << 16 |
+        channel(color.a) << 24
+}
 
-        // TODO use enum for all the attrib locs
-
-        vao.enable_attribute(2, VertexAttribute {
-            offset: offset_of!(Self, uv),
-            .. base
-        });
-        vao.enable_attribute(3, VertexAttribute {
-            size: 2,
-            offset: offset_of!(Self, transform) +
-                    offset_of!(Transform2d, position),
-            .. base
-        });
-        vao.enable_attribute(4, VertexAttribute {
-            size: 2,
-            offset: offset_of!(Self, transform) +
-                    offset_of!(Transform2d, scale),
-            .. base
-        });
-        vao.enable_attribute(5, VertexAttribute {
-            size: 1,
-            offset: offset_of!(Self, transform) +
-                    offset_of!(Transform2d, rotation),
-            .. base
-        });
-        vao.enable_attribute(6, VertexAttribute {
-            offset: offset_of!(Self, color),
-            .. base
-        });
-    }
+/// Meshes every `Spritebatch` has registered.
+#[derive(Copy, Clone, Debug)]
+pub struct SpriteMeshes {
+    pub quad: MeshId,
+    pub quad_centered: MeshId,
+    /// Centered, diameter of 1.
+    pub circle: MeshId,
 }
 
 /// 2d instancer
 pub struct Spritebatch {
-    instancer: Instancer<SbSprite>,
-    quad: Mesh<Vertex2d>,
-    quad_centered: Mesh<Vertex2d>,
+    instancer: Instancer<SbSprite, Vertex2d>,
+    meshes: SpriteMeshes,
 }
 
 impl Spritebatch {
+    const CIRCLE_RESOLUTION: usize = 32;
+
     pub fn new(size: usize) -> Self {
-        let instancer = Instancer::new(size);
-        let mut quad = Mesh2d::new(Vertex2d::quad(false),
-                                   Vec::new(),
-                                   gl::STATIC_DRAW,
-                                   gl::TRIANGLE_STRIP);
-        let mut quad_centered = Mesh2d::new(Vertex2d::quad(true),
-                                            Vec::new(),
-                                            gl::STATIC_DRAW,
-                                            gl::TRIANGLE_STRIP);
-
-        instancer.make_mesh_compatible(&mut quad);
-        instancer.make_mesh_compatible(&mut quad_centered);
+        let mut instancer = Instancer::new(size);
+        let quad = Mesh2d::new(Vertex2d::quad(false),
+                               Vec::new(),
+                               BufferUsage::StaticDraw,
+                               Primitive::TriangleStrip).unwrap();
+        let quad_centered = Mesh2d::new(Vertex2d::quad(true),
+                                        Vec::new(),
+                                        BufferUsage::StaticDraw,
+                                        Primitive::TriangleStrip).unwrap();
+        let circle = Mesh2d::new(Vertex2d::circle(Self::CIRCLE_RESOLUTION),
+                                 Vec::new(),
+                                 BufferUsage::StaticDraw,
+                                 Primitive::TriangleFan).unwrap();
+
+        let meshes = SpriteMeshes {
+            quad: instancer.register_mesh(quad).unwrap(),
+            quad_centered: instancer.register_mesh(quad_centered).unwrap(),
+            circle: instancer.register_mesh(circle).unwrap(),
+        };
 
         Self {
             instancer,
-            quad,
-            quad_centered,
+            meshes,
         }
     }
 
-    pub fn bind<'a>(&'a mut self, centered_quad: bool) -> BoundInstancer<'a, SbSprite, Vertex2d> {
+    /// Registers a custom mesh sprites can be drawn with.
+    pub fn register_mesh(&mut self, mesh: Mesh2d) -> MeshId {
+        self.instancer.register_mesh(mesh).unwrap()
+    }
+
+    pub fn meshes(&self) -> SpriteMeshes {
+        self.meshes
+    }
+
+    pub fn bind(&mut self, centered_quad: bool) -> BoundInstancer<'_, SbSprite, Vertex2d> {
         self.instancer.bind(if centered_quad {
-                &self.quad_centered
+                self.meshes.quad_centered
             } else {
-                &self.quad
+                self.meshes.quad
             })
     }
+
+    pub fn bind_mesh(&mut self, mesh: MeshId) -> BoundInstancer<'_, SbSprite, Vertex2d> {
+        self.instancer.bind(mesh)
+    }
 }
 
 impl<'a> BoundInstancer<'a, SbSprite, Vertex2d> {
@@ -129,15 +147,68 @@ impl<'a> BoundInstancer<'a, SbSprite, Vertex2d> {
         //        cant do this without diffuse location
 
         let mut x = 0.;
-        let font_h = font.texture().height() as f32;
         for ch in text.chars() {
-            let region_w = font.region(ch).width() as f32;
-            let sp = self.pull_default();
-            sp.uv = font.uv_region(ch);
-            sp.transform.position.x = x;
-            sp.transform.scale.x = region_w;
-            sp.transform.scale.y = font_h;
-            x += region_w + 1.;
+            let glyph = font.glyph(ch);
+            if !glyph.is_empty() {
+                let sp = self.pull_default();
+                sp.uv = glyph.uv;
+                sp.transform.position.x = x + glyph.offset.x;
+                sp.transform.position.y = glyph.offset.y;
+                sp.transform.scale.x = glyph.region.width() as f32;
+                sp.transform.scale.y = glyph.region.height() as f32;
+            }
+            x += glyph.advance;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use memoffset::offset_of;
+
+    use crate::gfx::{
+        AttributeKind,
+        VertexArray,
+        VertexAttribute,
+    };
+
+    use super::*;
+
+    #[test]
+    fn sprite_attribute_layout() {
+        let mut vao = VertexArray::recording();
+        SbSprite::set_attributes(&mut vao).unwrap();
+
+        let attrib = | size, ty, kind, offset | VertexAttribute {
+            size,
+            ty,
+            kind,
+            stride: mem::size_of::<SbSprite>(),
+            offset,
+            divisor: 1,
+        };
+        let float = | size, offset | attrib(size, gl::FLOAT, AttributeKind::Float, offset);
+        let transform = offset_of!(SbSprite, transform);
+
+        // same as the shaders, and the layout that was written out by hand before the derive
+        assert_eq!(vao.recorded(), &[
+            (2, float(4, offset_of!(SbSprite, uv))),
+            (3, float(2, transform + offset_of!(Transform2d, position))),
+            (4, float(2, transform + offset_of!(Transform2d, scale))),
+            (5, float(1, transform + offset_of!(Transform2d, rotation))),
+            (6, float(4, offset_of!(SbSprite, color))),
+            (13, attrib(4, gl::UNSIGNED_INT, AttributeKind::Integer, offset_of!(SbSprite, corners))),
+        ][..]);
+    }
+
+    #[test]
+    fn pack_color_channels() {
+        assert_eq!(pack_color(Color::new_rgba(1., 0., 0., 0.)), 0x000000ff);
+        assert_eq!(pack_color(Color::new_rgba(0., 1., 0., 0.)), 0x0000ff00);
+        assert_eq!(pack_color(Color::new_rgba(0., 0., 1., 0.)), 0x00ff0000);
+        assert_eq!(pack_color(Color::new_rgba(0., 0., 0., 1.)), 0xff000000);
+        assert_eq!(pack_color(Color::new_rgba(0.5, 2., -1., 1.)), 0xff00ff80);
+    }
+}
@@ -0,0 +1,536 @@
+use std::{
+    f32::consts,
+    mem,
+};
+
+use nalgebra_glm as glm;
+
+use super::{
+    dedup_ring,
+    signed_area,
+};
+
+//
+
+/// How segments of a polyline are connected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Bevel,
+    Round,
+}
+
+/// How the ends of an open polyline are drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends exactly at the end point.
+    Butt,
+    /// Extends past the end point by half the thickness.
+    Square,
+    Round,
+}
+
+/// Where the thickness of a closed outline goes, relative to the shape's edge.
+/// Open polylines are always centered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StrokeAlign {
+    Inner,
+    Center,
+    Outer,
+}
+
+/// Dash pattern, lengths in the same units as the stroked points,
+///   so the pattern scales with any transform applied to the shape.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Dash {
+    pub on: f32,
+    pub off: f32,
+    /// How far into the pattern the line starts.
+    pub offset: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub thickness: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub align: StrokeAlign,
+    /// Max ratio of miter length to half the thickness,
+    ///   sharper corners are beveled.
+    pub miter_limit: f32,
+    /// Each dash is capped with `cap`.
+    pub dash: Option<Dash>,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            thickness: 2.,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            align: StrokeAlign::Center,
+            miter_limit: 4.,
+            dash: None,
+        }
+    }
+}
+
+//
+
+/// Default max distance in pixels curves are allowed to stray from the true curve.
+pub const DEFAULT_TOLERANCE: f32 = 0.25;
+
+const MAX_ARC_SEGMENTS: usize = 1024;
+
+const EPSILON: f32 = 0.0001;
+
+/// Number of segments needed to draw an arc of `radius` and `angle` in radians,
+///   such that no segment strays more than `tolerance` from the arc.
+pub fn arc_segments(radius: f32, angle: f32, tolerance: f32) -> usize {
+    let tolerance = tolerance.max(EPSILON);
+    if radius <= tolerance {
+        return 1;
+    }
+    let step = 2. * (1. - tolerance / radius).acos();
+    ((angle.abs() / step).ceil() as usize).clamp(1, MAX_ARC_SEGMENTS)
+}
+
+/// Triangles of a filled arc, angles in radians.
+pub(crate) fn push_fan(out: &mut Vec<glm::Vec2>,
+                       center: glm::Vec2,
+                       radius: f32,
+                       start: f32,
+                       sweep: f32,
+                       tolerance: f32
+) {
+    let segments = arc_segments(radius, sweep, tolerance);
+    let point = | at: f32 | center + glm::vec2(at.cos(), at.sin()) * radius;
+    let mut last = point(start);
+    for i in 1..=segments {
+        let next = point(start + sweep * i as f32 / segments as f32);
+        out.push(center);
+        out.push(last);
+        out.push(next);
+        last = next;
+    }
+}
+
+/// Two triangles, `a` `b` `c` `d` in order around the quad.
+pub(crate) fn push_quad(out: &mut Vec<glm::Vec2>,
+                        a: glm::Vec2,
+                        b: glm::Vec2,
+                        c: glm::Vec2,
+                        d: glm::Vec2
+) {
+    out.extend_from_slice(&[a, b, c, a, c, d]);
+}
+
+fn perpendicular(v: glm::Vec2) -> glm::Vec2 {
+    glm::vec2(-v.y, v.x)
+}
+
+//
+
+/// Tessellates a stroke along `points` into a list of triangles, appended to `out`.
+/// Round joins and caps stay within `tolerance` of a true arc.
+/// note: triangles overlap at joins, so translucent strokes will show darker corners
+pub fn stroke_polyline(points: &[glm::Vec2],
+                       closed: bool,
+                       style: &StrokeStyle,
+                       tolerance: f32,
+                       out: &mut Vec<glm::Vec2>
+) {
+    let offset;
+    let points = match style.align {
+        StrokeAlign::Inner if closed => {
+            offset = offset_ring(points, -style.thickness / 2.);
+            &offset[..]
+        }
+        StrokeAlign::Outer if closed => {
+            offset = offset_ring(points, style.thickness / 2.);
+            &offset[..]
+        }
+        _ => points,
+    };
+
+    match style.dash {
+        Some(dash) if dash.on > 0. && dash.off > 0. => {
+            let mut path = points.to_vec();
+            if closed && !points.is_empty() {
+                path.push(points[0]);
+            }
+            for run in dash_runs(&path, &dash) {
+                stroke_solid(&run, false, style, tolerance, out);
+            }
+        }
+        _ => stroke_solid(points, closed, style, tolerance, out),
+    }
+}
+
+/// Moves every edge of a closed ring outwards by `distance`, or inwards if negative.
+/// note: inner offsets larger than the shape will turn it inside out
+pub(crate) fn offset_ring(points: &[glm::Vec2], distance: f32) -> Vec<glm::Vec2> {
+    // limits spikes at very sharp corners
+    const MAX_SCALE: f32 = 4.;
+
+    let ring = dedup_ring(points);
+    let n = ring.len();
+    if n < 3 {
+        return ring;
+    }
+
+    // outwards is to the right of each edge for counter clockwise rings
+    let side = if signed_area(&ring) > 0. { -1. } else { 1. };
+    let normal = | a: glm::Vec2, b: glm::Vec2 | perpendicular((b - a).normalize()) * side;
+
+    (0..n).map(| i | {
+              let prev = ring[(i + n - 1) % n];
+              let p = ring[i];
+              let next = ring[(i + 1) % n];
+              let n0 = normal(prev, p);
+              let mid = n0 + normal(p, next);
+              let len2 = mid.norm_squared();
+              if len2 < EPSILON {
+                  p + n0 * distance
+              } else {
+                  // 1 / cos of half the angle between the normals
+                  let scale = (2. / len2.sqrt()).min(MAX_SCALE);
+                  p + mid.normalize() * (distance * scale)
+              }
+          })
+          .collect()
+}
+
+/// Splits a polyline into the parts covered by the "on" sections of a dash pattern.
+fn dash_runs(points: &[glm::Vec2], dash: &Dash) -> Vec<Vec<glm::Vec2>> {
+    let period = dash.on + dash.off;
+    let mut phase = dash.offset.rem_euclid(period);
+    let mut on = phase < dash.on;
+
+    let mut runs = Vec::new();
+    let mut current = Vec::new();
+    if on && !points.is_empty() {
+        current.push(points[0]);
+    }
+
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let len = glm::distance(&a, &b);
+        if len < EPSILON {
+            continue;
+        }
+
+        let mut at = 0.;
+        loop {
+            let boundary = if on { dash.on } else { period };
+            let left = boundary - phase;
+            if at + left >= len {
+                phase += len - at;
+                break;
+            }
+
+            at += left;
+            let p = a + (b - a) * (at / len);
+            current.push(p);
+            if on {
+                runs.push(mem::take(&mut current));
+                phase = dash.on;
+            } else {
+                phase = 0.;
+            }
+            on = !on;
+        }
+
+        if on {
+            current.push(b);
+        }
+    }
+
+    if current.len() > 1 {
+        runs.push(current);
+    }
+    runs
+}
+
+fn stroke_solid(points: &[glm::Vec2],
+                closed: bool,
+                style: &StrokeStyle,
+                tolerance: f32,
+                out: &mut Vec<glm::Vec2>
+) {
+    let hw = style.thickness / 2.;
+    if hw <= 0. {
+        return;
+    }
+
+    let mut pts: Vec<glm::Vec2> = Vec::with_capacity(points.len());
+    for p in points {
+        let duplicate = match pts.last() {
+            Some(last) => glm::distance2(last, p) <= EPSILON * EPSILON,
+            None => false,
+        };
+        if !duplicate {
+            pts.push(*p);
+        }
+    }
+    if closed && pts.len() > 2 && glm::distance2(&pts[0], &pts[pts.len() - 1]) <= EPSILON * EPSILON {
+        pts.pop();
+    }
+
+    let n = pts.len();
+    if n < 2 {
+        // a single point only draws caps
+        if let Some(&p) = pts.first() {
+            match style.cap {
+                LineCap::Butt => {}
+                LineCap::Square => {
+                    push_quad(out,
+                              p + glm::vec2(-hw, -hw),
+                              p + glm::vec2(hw, -hw),
+                              p + glm::vec2(hw, hw),
+                              p + glm::vec2(-hw, hw));
+                }
+                LineCap::Round => push_fan(out, p, hw, 0., consts::PI * 2., tolerance),
+            }
+        }
+        return;
+    }
+
+    let closed = closed && n > 2;
+
+    let segment_count = if closed { n } else { n - 1 };
+    for i in 0..segment_count {
+        let a = pts[i];
+        let b = pts[(i + 1) % n];
+        let offset = perpendicular((b - a).normalize()) * hw;
+        push_quad(out, a + offset, b + offset, b - offset, a - offset);
+    }
+
+    let joins = if closed { 0..n } else { 1..(n - 1) };
+    for i in joins {
+        push_join(out, pts[(i + n - 1) % n], pts[i], pts[(i + 1) % n], hw, style, tolerance);
+    }
+
+    if !closed {
+        push_cap(out, pts[0], (pts[0] - pts[1]).normalize(), hw, style.cap, tolerance);
+        push_cap(out, pts[n - 1], (pts[n - 1] - pts[n - 2]).normalize(), hw, style.cap, tolerance);
+    }
+}
+
+/// `dir` points away from the line.
+fn push_cap(out: &mut Vec<glm::Vec2>,
+            p: glm::Vec2,
+            dir: glm::Vec2,
+            hw: f32,
+            cap: LineCap,
+            tolerance: f32
+) {
+    let offset = perpendicular(dir) * hw;
+    match cap {
+        LineCap::Butt => {}
+        LineCap::Square => {
+            let ext = dir * hw;
+            push_quad(out, p + offset, p + offset + ext, p - offset + ext, p - offset);
+        }
+        LineCap::Round => {
+            push_fan(out, p, hw, offset.y.atan2(offset.x), -consts::PI, tolerance);
+        }
+    }
+}
+
+/// Fills the gap on the outside of the corner at `p`.
+fn push_join(out: &mut Vec<glm::Vec2>,
+             prev: glm::Vec2,
+             p: glm::Vec2,
+             next: glm::Vec2,
+             hw: f32,
+             style: &StrokeStyle,
+             tolerance: f32
+) {
+    let d0 = (p - prev).normalize();
+    let d1 = (next - p).normalize();
+    let cross = d0.x * d1.y - d0.y * d1.x;
+
+    if cross.abs() < EPSILON {
+        // straight, or turns back on itself
+        if glm::dot(&d0, &d1) < 0. && style.join == LineJoin::Round {
+            push_cap(out, p, d0, hw, LineCap::Round, tolerance);
+        }
+        return;
+    }
+
+    let side = if cross > 0. { -1. } else { 1. };
+    let o0 = perpendicular(d0) * side * hw;
+    let o1 = perpendicular(d1) * side * hw;
+
+    match style.join {
+        LineJoin::Bevel => {
+            out.extend_from_slice(&[p, p + o0, p + o1]);
+        }
+        LineJoin::Miter => {
+            let mid = o0 + o1;
+            // 1 / cos of half the angle between the offsets
+            let ratio = 2. * hw / mid.norm();
+            if ratio > style.miter_limit {
+                out.extend_from_slice(&[p, p + o0, p + o1]);
+            } else {
+                let tip = p + mid.normalize() * (hw * ratio);
+                out.extend_from_slice(&[p, p + o0, tip, p, tip, p + o1]);
+            }
+        }
+        LineJoin::Round => {
+            let start = o0.y.atan2(o0.x);
+            let mut sweep = o1.y.atan2(o1.x) - start;
+            if sweep > consts::PI {
+                sweep -= consts::PI * 2.;
+            } else if sweep < -consts::PI {
+                sweep += consts::PI * 2.;
+            }
+            push_fan(out, p, hw, start, sweep, tolerance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::area;
+
+    fn all_finite(tris: &[glm::Vec2]) -> bool {
+        tris.iter().all(| p | p.x.is_finite() && p.y.is_finite())
+    }
+
+    fn style(cap: LineCap) -> StrokeStyle {
+        StrokeStyle {
+            cap,
+            .. StrokeStyle::default()
+        }
+    }
+
+    fn line(to: f32) -> Vec<glm::Vec2> {
+        vec![glm::vec2(0., 0.), glm::vec2(to, 0.)]
+    }
+
+    #[test]
+    fn straight_line_area() {
+        let mut out = Vec::new();
+        stroke_polyline(&line(10.), false, &style(LineCap::Butt), DEFAULT_TOLERANCE, &mut out);
+        assert!((area(&out) - 20.).abs() < 0.001);
+
+        out.clear();
+        stroke_polyline(&line(10.), false, &style(LineCap::Square), DEFAULT_TOLERANCE, &mut out);
+        assert!((area(&out) - 24.).abs() < 0.001);
+    }
+
+    #[test]
+    fn collinear_and_duplicate_points() {
+        let points = [
+            glm::vec2(0., 0.),
+            glm::vec2(5., 0.),
+            glm::vec2(5., 0.),
+            glm::vec2(7., 0.),
+            glm::vec2(10., 0.),
+        ];
+        let mut out = Vec::new();
+        stroke_polyline(&points, false, &style(LineCap::Butt), DEFAULT_TOLERANCE, &mut out);
+        assert!(all_finite(&out));
+        assert!((area(&out) - 20.).abs() < 0.001);
+    }
+
+    #[test]
+    fn degenerate_segments() {
+        let point = [glm::vec2(3., 3.); 3];
+        let mut out = Vec::new();
+        stroke_polyline(&point, false, &style(LineCap::Butt), DEFAULT_TOLERANCE, &mut out);
+        assert!(out.is_empty());
+
+        stroke_polyline(&point, false, &style(LineCap::Square), DEFAULT_TOLERANCE, &mut out);
+        assert!((area(&out) - 4.).abs() < 0.001);
+
+        out.clear();
+        stroke_polyline(&point, false, &style(LineCap::Round), 0.001, &mut out);
+        assert!(all_finite(&out));
+        assert!((area(&out) - consts::PI).abs() < 0.01);
+
+        out.clear();
+        stroke_polyline(&[], false, &style(LineCap::Round), DEFAULT_TOLERANCE, &mut out);
+        stroke_polyline(&line(10.), false, &StrokeStyle { thickness: 0., .. style(LineCap::Round) },
+                        DEFAULT_TOLERANCE, &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn turning_back_stays_finite() {
+        let points = [glm::vec2(0., 0.), glm::vec2(10., 0.), glm::vec2(0., 0.)];
+        for join in &[LineJoin::Miter, LineJoin::Bevel, LineJoin::Round] {
+            let mut out = Vec::new();
+            let style = StrokeStyle {
+                join: *join,
+                .. StrokeStyle::default()
+            };
+            stroke_polyline(&points, false, &style, DEFAULT_TOLERANCE, &mut out);
+            assert!(all_finite(&out));
+        }
+    }
+
+    #[test]
+    fn offset_ring_either_winding() {
+        let ccw = vec![glm::vec2(0., 0.), glm::vec2(10., 0.), glm::vec2(10., 10.), glm::vec2(0., 10.)];
+        let mut cw = ccw.clone();
+        cw.reverse();
+
+        for ring in &[ccw, cw] {
+            assert!((signed_area(&offset_ring(ring, 1.)).abs() - 144.).abs() < 0.001);
+            assert!((signed_area(&offset_ring(ring, -1.)).abs() - 64.).abs() < 0.001);
+        }
+    }
+
+    fn dash(on: f32, off: f32, offset: f32) -> Dash {
+        Dash {
+            on,
+            off,
+            offset,
+        }
+    }
+
+    fn run_xs(runs: &[Vec<glm::Vec2>]) -> Vec<(f32, f32)> {
+        runs.iter().map(| run | (run[0].x, run[run.len() - 1].x)).collect()
+    }
+
+    #[test]
+    fn dash_runs_basic() {
+        let runs = dash_runs(&line(10.), &dash(2., 2., 0.));
+        assert_eq!(run_xs(&runs), vec![(0., 2.), (4., 6.), (8., 10.)]);
+    }
+
+    #[test]
+    fn dash_phase_wraps() {
+        // offsets a whole number of periods apart give the same pattern
+        let expected = vec![(0., 1.), (3., 5.), (7., 9.)];
+        for offset in &[1., 5., 9., -3., -7.] {
+            let runs = dash_runs(&line(10.), &dash(2., 2., *offset));
+            assert_eq!(run_xs(&runs), expected, "offset {}", offset);
+        }
+
+        // starting in the off part
+        let runs = dash_runs(&line(10.), &dash(2., 2., 2.));
+        assert_eq!(run_xs(&runs), vec![(2., 4.), (6., 8.)]);
+    }
+
+    #[test]
+    fn dash_phase_carries_across_corners() {
+        let points = [glm::vec2(0., 0.), glm::vec2(3., 0.), glm::vec2(3., 3.)];
+        let runs = dash_runs(&points, &dash(2., 2., 0.));
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0], vec![glm::vec2(0., 0.), glm::vec2(2., 0.)]);
+        assert_eq!(runs[1], vec![glm::vec2(3., 1.), glm::vec2(3., 3.)]);
+    }
+
+    #[test]
+    fn dash_skips_degenerate_segments() {
+        let points = [glm::vec2(0., 0.), glm::vec2(1., 0.), glm::vec2(1., 0.), glm::vec2(3., 0.)];
+        let runs = dash_runs(&points, &dash(2., 2., 0.));
+        assert_eq!(run_xs(&runs), vec![(0., 2.)]);
+        assert!(runs.iter().flatten().all(| p | p.x.is_finite()));
+    }
+}
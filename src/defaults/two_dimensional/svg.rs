@@ -0,0 +1,984 @@
+use nalgebra_glm as glm;
+use roxmltree::{
+    Document,
+    Node,
+};
+
+use crate::{
+    gfx::{
+        BufferUsage,
+        Mesh,
+        Primitive,
+    },
+    math::Color,
+};
+
+use super::{
+    Dash,
+    FillRule,
+    LineCap,
+    LineJoin,
+    Path,
+    ColoredVertex2d,
+    StrokeStyle,
+};
+
+//
+
+// TODO
+// gradients, use, css stylesheets, text
+
+#[derive(Copy, Clone, Debug)]
+pub struct SvgFill {
+    pub color: Color,
+    pub rule: FillRule,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SvgStroke {
+    pub color: Color,
+    pub style: StrokeStyle,
+}
+
+/// One drawable element, with its transforms already applied to the path.
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    pub path: Path,
+    pub fill: Option<SvgFill>,
+    pub stroke: Option<SvgStroke>,
+}
+
+/// Shapes imported from an SVG document, in drawing order.
+///
+/// Supports paths, rects, circles, ellipses, lines, polygons and polylines,
+///   fill and stroke attributes, transforms, groups, and the root `viewBox`.
+/// Anything else is skipped.
+#[derive(Clone, Debug, Default)]
+pub struct Svg {
+    pub width: f32,
+    pub height: f32,
+    pub shapes: Vec<SvgShape>,
+}
+
+impl Svg {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let doc = Document::parse(text).map_err(| err | err.to_string())?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "svg" {
+            return Err(format!("root element is not svg: {}", root.tag_name().name()));
+        }
+
+        let view_box = root.attribute("viewBox").map(parse_numbers);
+        let view_box = match view_box {
+            Some(v) if v.len() == 4 && v[2] > 0. && v[3] > 0. => Some(v),
+            _ => None,
+        };
+
+        let length = | name: &str | root.attribute(name).and_then(parse_length);
+        // a missing side follows the aspect ratio of the view box
+        let (width, height) = match (length("width"), length("height"), &view_box) {
+            (Some(w), Some(h), _) => (w, h),
+            (Some(w), None, Some(v)) => (w, w * v[3] / v[2]),
+            (None, Some(h), Some(v)) => (h * v[2] / v[3], h),
+            (None, None, Some(v)) => (v[2], v[3]),
+            (w, h, None) => (w.unwrap_or(0.), h.unwrap_or(0.)),
+        };
+
+        let mut style = Style::default();
+        if let Some(v) = view_box {
+            // preserveAspectRatio="xMidYMid meet"
+            let scale = (width / v[2]).min(height / v[3]);
+            let offset_x = (width - v[2] * scale) / 2. - v[0] * scale;
+            let offset_y = (height - v[3] * scale) / 2. - v[1] * scale;
+            style.transform = glm::mat3(scale, 0., offset_x,
+                                        0., scale, offset_y,
+                                        0., 0., 1.);
+        }
+
+        let mut ret = Self {
+            width,
+            height,
+            shapes: Vec::new(),
+        };
+        ret.walk(root, style);
+        Ok(ret)
+    }
+
+    fn walk(&mut self, node: Node, parent: Style) {
+        let mut style = parent;
+        style.apply(node);
+
+        let path = match node.tag_name().name() {
+            "svg" | "g" | "a" => {
+                for child in node.children().filter(| child | child.is_element()) {
+                    self.walk(child, style);
+                }
+                return;
+            }
+            "path" => parse_path_data(node.attribute("d").unwrap_or("")),
+            "rect" => rect_path(node),
+            "circle" => {
+                let r = attribute_length(node, "r");
+                ellipse_path(attribute_length(node, "cx"), attribute_length(node, "cy"), r, r)
+            }
+            "ellipse" => ellipse_path(attribute_length(node, "cx"),
+                                      attribute_length(node, "cy"),
+                                      attribute_length(node, "rx"),
+                                      attribute_length(node, "ry")),
+            "line" => {
+                let mut path = Path::new();
+                path.move_to(attribute_length(node, "x1"), attribute_length(node, "y1"))
+                    .line_to(attribute_length(node, "x2"), attribute_length(node, "y2"));
+                path
+            }
+            "polygon" | "polyline" => {
+                let points = parse_numbers(node.attribute("points").unwrap_or(""));
+                let mut path = Path::new();
+                for (i, pair) in points.chunks_exact(2).enumerate() {
+                    if i == 0 {
+                        path.move_to(pair[0], pair[1]);
+                    } else {
+                        path.line_to(pair[0], pair[1]);
+                    }
+                }
+                if node.tag_name().name() == "polygon" && !path.is_empty() {
+                    path.close();
+                }
+                path
+            }
+            _ => return,
+        };
+
+        if path.is_empty() {
+            return;
+        }
+        self.shapes.push(style.shape(path));
+    }
+
+    /// Triangles of every shape, colors multiplied by `tint`, appended to `out`.
+    /// `tolerance` is in the svg's units.
+    /// uvs span the svg's width and height.
+    pub fn tessellate<E: Extend<ColoredVertex2d>>(&self, tolerance: f32, tint: Color, out: &mut E) {
+        let inv_w = if self.width > 0. { self.width.recip() } else { 0. };
+        let inv_h = if self.height > 0. { self.height.recip() } else { 0. };
+
+        let mut scratch = Vec::new();
+        let mut push = | points: &mut Vec<glm::Vec2>, color: Color | {
+            let color = Color::new_rgba(color.r * tint.r,
+                                        color.g * tint.g,
+                                        color.b * tint.b,
+                                        color.a * tint.a);
+            out.extend(points.drain(..).map(| p | ColoredVertex2d {
+                position: p,
+                uv:       glm::vec2(p.x * inv_w, p.y * inv_h),
+                color,
+            }));
+        };
+
+        for shape in self.shapes.iter() {
+            if let Some(fill) = shape.fill {
+                shape.path.fill(fill.rule, tolerance, &mut scratch);
+                push(&mut scratch, fill.color);
+            }
+            if let Some(stroke) = shape.stroke {
+                shape.path.stroke(&stroke.style, tolerance, &mut scratch);
+                push(&mut scratch, stroke.color);
+            }
+        }
+    }
+
+    /// Tessellates every shape into a static mesh, to be drawn with a program
+    ///   that reads vertex colors, like `default_program()`.
+    pub fn bake(&self, tolerance: f32) -> Result<Mesh<ColoredVertex2d>, String> {
+        let mut vertices = Vec::new();
+        self.tessellate(tolerance, Color::white(), &mut vertices);
+        Mesh::new(vertices,
+                  Vec::new(),
+                  BufferUsage::StaticDraw,
+                  Primitive::Triangles)
+    }
+}
+
+//
+
+/// Presentation attributes, inherited by children.
+#[derive(Copy, Clone, Debug)]
+struct Style {
+    transform: glm::Mat3,
+    fill: Option<Color>,
+    fill_rule: FillRule,
+    fill_opacity: f32,
+    stroke: Option<Color>,
+    stroke_opacity: f32,
+    stroke_style: StrokeStyle,
+    dash: Option<(f32, f32)>,
+    dash_offset: f32,
+    // note: group opacity is applied to each shape separately
+    opacity: f32,
+    // opacity inherited from the parent, so the node's own opacity replaces rather than stacks
+    parent_opacity: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            transform: glm::Mat3::identity(),
+            fill: Some(Color::black()),
+            fill_rule: FillRule::NonZero,
+            fill_opacity: 1.,
+            stroke: None,
+            stroke_opacity: 1.,
+            stroke_style: StrokeStyle {
+                thickness: 1.,
+                ..StrokeStyle::default()
+            },
+            dash: None,
+            dash_offset: 0.,
+            opacity: 1.,
+            parent_opacity: 1.,
+        }
+    }
+}
+
+impl Style {
+    fn apply(&mut self, node: Node) {
+        self.parent_opacity = self.opacity;
+        for attr in node.attributes() {
+            self.property(attr.name(), attr.value());
+        }
+        if let Some(style) = node.attribute("style") {
+            for declaration in style.split(';') {
+                let mut parts = declaration.splitn(2, ':');
+                if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                    self.property(name.trim(), value.trim());
+                }
+            }
+        }
+        if let Some(transform) = node.attribute("transform") {
+            self.transform *= parse_transform(transform);
+        }
+    }
+
+    /// Unknown properties and invalid values are ignored.
+    fn property(&mut self, name: &str, value: &str) {
+        match name {
+            "fill" => {
+                if let Some(paint) = parse_paint(value) {
+                    self.fill = paint;
+                }
+            }
+            "fill-rule" => match value {
+                "nonzero" => self.fill_rule = FillRule::NonZero,
+                "evenodd" => self.fill_rule = FillRule::EvenOdd,
+                _ => {}
+            },
+            "fill-opacity" => {
+                if let Some(v) = parse_length(value) {
+                    self.fill_opacity = v.clamp(0., 1.);
+                }
+            }
+            "stroke" => {
+                if let Some(paint) = parse_paint(value) {
+                    self.stroke = paint;
+                }
+            }
+            "stroke-opacity" => {
+                if let Some(v) = parse_length(value) {
+                    self.stroke_opacity = v.clamp(0., 1.);
+                }
+            }
+            "stroke-width" => {
+                if let Some(v) = parse_length(value) {
+                    self.stroke_style.thickness = v.max(0.);
+                }
+            }
+            "stroke-linecap" => match value {
+                "butt" => self.stroke_style.cap = LineCap::Butt,
+                "round" => self.stroke_style.cap = LineCap::Round,
+                "square" => self.stroke_style.cap = LineCap::Square,
+                _ => {}
+            },
+            "stroke-linejoin" => match value {
+                "miter" | "miter-clip" | "arcs" => self.stroke_style.join = LineJoin::Miter,
+                "round" => self.stroke_style.join = LineJoin::Round,
+                "bevel" => self.stroke_style.join = LineJoin::Bevel,
+                _ => {}
+            },
+            "stroke-miterlimit" => {
+                if let Some(v) = parse_length(value) {
+                    self.stroke_style.miter_limit = v.max(1.);
+                }
+            }
+            "stroke-dasharray" => {
+                // only the first dash and gap are used
+                let v = parse_numbers(value);
+                self.dash = match v.len() {
+                    0 => None,
+                    1 => Some((v[0], v[0])),
+                    _ => Some((v[0], v[1])),
+                };
+            }
+            "stroke-dashoffset" => {
+                if let Some(v) = parse_length(value) {
+                    self.dash_offset = v;
+                }
+            }
+            "opacity" => {
+                if let Some(v) = parse_length(value) {
+                    self.opacity = self.parent_opacity * v.clamp(0., 1.);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn shape(&self, mut path: Path) -> SvgShape {
+        path.transform(&self.transform);
+
+        let with_alpha = | color: Color, opacity: f32 | {
+            Color::new_rgba(color.r, color.g, color.b, color.a * opacity * self.opacity)
+        };
+
+        // strokes are scaled by the average scale of the transform
+        let m = &self.transform;
+        let scale = (m[(0, 0)] * m[(1, 1)] - m[(0, 1)] * m[(1, 0)]).abs().sqrt();
+
+        let stroke = self.stroke.map(| color | {
+            let mut style = self.stroke_style;
+            style.thickness *= scale;
+            style.dash = self.dash.map(| (on, off) | Dash {
+                on: on * scale,
+                off: off * scale,
+                offset: self.dash_offset * scale,
+            });
+            SvgStroke {
+                color: with_alpha(color, self.stroke_opacity),
+                style,
+            }
+        });
+
+        SvgShape {
+            path,
+            fill: self.fill.map(| color | SvgFill {
+                color: with_alpha(color, self.fill_opacity),
+                rule: self.fill_rule,
+            }),
+            stroke: stroke.filter(| stroke | stroke.style.thickness > 0.),
+        }
+    }
+}
+
+//
+
+fn attribute_length(node: Node, name: &str) -> f32 {
+    node.attribute(name).and_then(parse_length).unwrap_or(0.)
+}
+
+fn rect_path(node: Node) -> Path {
+    let x = attribute_length(node, "x");
+    let y = attribute_length(node, "y");
+    let w = attribute_length(node, "width");
+    let h = attribute_length(node, "height");
+
+    let mut path = Path::new();
+    if w <= 0. || h <= 0. {
+        return path;
+    }
+
+    // a missing radius takes the other one
+    let rx = node.attribute("rx").and_then(parse_length);
+    let ry = node.attribute("ry").and_then(parse_length);
+    let (rx, ry) = match (rx, ry) {
+        (Some(rx), Some(ry)) => (rx, ry),
+        (Some(r), None) | (None, Some(r)) => (r, r),
+        (None, None) => (0., 0.),
+    };
+    let rx = rx.clamp(0., w / 2.);
+    let ry = ry.clamp(0., h / 2.);
+
+    if rx == 0. || ry == 0. {
+        path.move_to(x, y)
+            .line_to(x + w, y)
+            .line_to(x + w, y + h)
+            .line_to(x, y + h)
+            .close();
+    } else {
+        path.move_to(x + rx, y)
+            .line_to(x + w - rx, y)
+            .arc_to(rx, ry, 0., false, true, x + w, y + ry)
+            .line_to(x + w, y + h - ry)
+            .arc_to(rx, ry, 0., false, true, x + w - rx, y + h)
+            .line_to(x + rx, y + h)
+            .arc_to(rx, ry, 0., false, true, x, y + h - ry)
+            .line_to(x, y + ry)
+            .arc_to(rx, ry, 0., false, true, x + rx, y)
+            .close();
+    }
+    path
+}
+
+fn ellipse_path(cx: f32, cy: f32, rx: f32, ry: f32) -> Path {
+    let mut path = Path::new();
+    if rx <= 0. || ry <= 0. {
+        return path;
+    }
+    path.move_to(cx + rx, cy)
+        .arc_to(rx, ry, 0., false, true, cx - rx, cy)
+        .arc_to(rx, ry, 0., false, true, cx + rx, cy)
+        .close();
+    path
+}
+
+//
+
+/// Reads numbers, flags and commands out of path data and attribute lists.
+struct Lexer<'a> {
+    text: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text: text.as_bytes(),
+            at: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.at).copied()
+    }
+
+    fn is_done(&mut self) -> bool {
+        self.skip_separators();
+        self.at >= self.text.len()
+    }
+
+    /// Whitespace, with at most one comma.
+    fn skip_separators(&mut self) {
+        let mut comma = false;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_whitespace() || (c == b',' && !comma) {
+                comma |= c == b',';
+                self.at += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.at;
+        let digits = | lexer: &mut Self | {
+            let from = lexer.at;
+            while matches!(lexer.peek(), Some(c) if c.is_ascii_digit()) {
+                lexer.at += 1;
+            }
+            lexer.at > from
+        };
+
+        if let Some(b'+') | Some(b'-') = self.peek() {
+            self.at += 1;
+        }
+        let mut any = digits(self);
+        if self.peek() == Some(b'.') {
+            self.at += 1;
+            any |= digits(self);
+        }
+        if !any {
+            self.at = start;
+            return None;
+        }
+
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            let mark = self.at;
+            self.at += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.at += 1;
+            }
+            if !digits(self) {
+                self.at = mark;
+            }
+        }
+
+        std::str::from_utf8(&self.text[start..self.at]).ok()?.parse().ok()
+    }
+
+    /// Arc flags can be written without separators, like `a1 1 0 00 1 1`.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let ret = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.at += 1;
+        Some(ret)
+    }
+
+    fn point(&mut self) -> Option<glm::Vec2> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Some(glm::vec2(x, y))
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let c = self.peek()?;
+        if c.is_ascii_alphabetic() && c != b'e' && c != b'E' {
+            self.at += 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_numbers(text: &str) -> Vec<f32> {
+    let mut lexer = Lexer::new(text);
+    let mut ret = Vec::new();
+    while let Some(n) = lexer.number() {
+        ret.push(n);
+    }
+    ret
+}
+
+/// Units are ignored, `10px` is `10`.
+fn parse_length(text: &str) -> Option<f32> {
+    Lexer::new(text.trim()).number()
+}
+
+/// Path data, like the `d` attribute of a `path`.
+/// Like browsers, stops at the first error and keeps what was parsed so far.
+pub fn parse_path_data(data: &str) -> Path {
+    let mut path = Path::new();
+    let mut lexer = Lexer::new(data);
+    let mut command: Option<u8> = None;
+    // control points of the previous segment, reflected by S and T
+    let mut last_cubic: Option<glm::Vec2> = None;
+    let mut last_quad: Option<glm::Vec2> = None;
+
+    while !lexer.is_done() {
+        if let Some(c) = lexer.command() {
+            command = Some(c);
+        }
+        let c = match command {
+            Some(c) => c,
+            None => break,
+        };
+
+        let current = path.current_point();
+        let base = if c.is_ascii_lowercase() { current } else { glm::vec2(0., 0.) };
+        let reflect = | control: Option<glm::Vec2> | match control {
+            Some(control) => current * 2. - control,
+            None => current,
+        };
+
+        let ok = (|| -> Option<()> {
+            let mut cubic = None;
+            let mut quad = None;
+            match c.to_ascii_uppercase() {
+                b'M' => {
+                    let p = base + lexer.point()?;
+                    path.move_to(p.x, p.y);
+                    // following pairs are lines
+                    command = Some(if c == b'm' { b'l' } else { b'L' });
+                }
+                b'Z' => {
+                    path.close();
+                    command = None;
+                }
+                b'L' => {
+                    let p = base + lexer.point()?;
+                    path.line_to(p.x, p.y);
+                }
+                b'H' => {
+                    let x = base.x + lexer.number()?;
+                    path.line_to(x, current.y);
+                }
+                b'V' => {
+                    let y = base.y + lexer.number()?;
+                    path.line_to(current.x, y);
+                }
+                b'C' => {
+                    let c1 = base + lexer.point()?;
+                    let c2 = base + lexer.point()?;
+                    let p = base + lexer.point()?;
+                    path.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+                    cubic = Some(c2);
+                }
+                b'S' => {
+                    let c1 = reflect(last_cubic);
+                    let c2 = base + lexer.point()?;
+                    let p = base + lexer.point()?;
+                    path.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+                    cubic = Some(c2);
+                }
+                b'Q' => {
+                    let c1 = base + lexer.point()?;
+                    let p = base + lexer.point()?;
+                    path.quad_to(c1.x, c1.y, p.x, p.y);
+                    quad = Some(c1);
+                }
+                b'T' => {
+                    let c1 = reflect(last_quad);
+                    let p = base + lexer.point()?;
+                    path.quad_to(c1.x, c1.y, p.x, p.y);
+                    quad = Some(c1);
+                }
+                b'A' => {
+                    let rx = lexer.number()?;
+                    let ry = lexer.number()?;
+                    let rotation = lexer.number()?;
+                    let large_arc = lexer.flag()?;
+                    let sweep = lexer.flag()?;
+                    let p = base + lexer.point()?;
+                    path.arc_to(rx, ry, rotation.to_radians(), large_arc, sweep, p.x, p.y);
+                }
+                _ => return None,
+            }
+            last_cubic = cubic;
+            last_quad = quad;
+            Some(())
+        })();
+
+        if ok.is_none() {
+            break;
+        }
+    }
+
+    path
+}
+
+/// An invalid transform list is the identity.
+fn parse_transform(text: &str) -> glm::Mat3 {
+    let mut ret = glm::Mat3::identity();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('(') {
+        let close = match rest[open..].find(')') {
+            Some(close) => open + close,
+            None => break,
+        };
+        let name = rest[..open].trim_matches(| c: char | c.is_whitespace() || c == ',');
+        let v = parse_numbers(&rest[(open + 1)..close]);
+        rest = &rest[(close + 1)..];
+
+        let arg = | i: usize, default: f32 | v.get(i).copied().unwrap_or(default);
+        let m = match (name, v.len()) {
+            ("matrix", 6) => glm::mat3(v[0], v[2], v[4],
+                                       v[1], v[3], v[5],
+                                       0., 0., 1.),
+            ("translate", 1..=2) => glm::mat3(1., 0., v[0],
+                                              0., 1., arg(1, 0.),
+                                              0., 0., 1.),
+            ("scale", 1..=2) => glm::mat3(v[0], 0., 0.,
+                                          0., arg(1, v[0]), 0.,
+                                          0., 0., 1.),
+            ("rotate", 1) | ("rotate", 3) => {
+                let (sin, cos) = v[0].to_radians().sin_cos();
+                let (cx, cy) = (arg(1, 0.), arg(2, 0.));
+                // rotate around the center
+                glm::mat3(cos, -sin, cx - cos * cx + sin * cy,
+                          sin, cos, cy - sin * cx - cos * cy,
+                          0., 0., 1.)
+            }
+            ("skewX", 1) => glm::mat3(1., v[0].to_radians().tan(), 0.,
+                                      0., 1., 0.,
+                                      0., 0., 1.),
+            ("skewY", 1) => glm::mat3(1., 0., 0.,
+                                      v[0].to_radians().tan(), 1., 0.,
+                                      0., 0., 1.),
+            _ => return glm::Mat3::identity(),
+        };
+        ret *= m;
+    }
+
+    ret
+}
+
+/// `Some(None)` for `none`, `None` if the paint can't be read.
+fn parse_paint(text: &str) -> Option<Option<Color>> {
+    let text = text.trim();
+    if text == "none" {
+        return Some(None);
+    }
+    parse_color(text).map(Some)
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    let rgb = | r: u8, g: u8, b: u8 | {
+        Color::new_rgba(r as f32 / 255., g as f32 / 255., b as f32 / 255., 1.)
+    };
+
+    if let Some(hex) = text.strip_prefix('#') {
+        let digit = | i: usize | u8::from_str_radix(hex.get(i..(i + 1))?, 16).ok();
+        let byte = | i: usize | u8::from_str_radix(hex.get(i..(i + 2))?, 16).ok();
+        return match hex.len() {
+            3 => Some(rgb(digit(0)? * 17, digit(1)? * 17, digit(2)? * 17)),
+            6 => Some(rgb(byte(0)?, byte(2)?, byte(4)?)),
+            _ => None,
+        };
+    }
+
+    if text.starts_with("rgb") {
+        let open = text.find('(')?;
+        let close = text.find(')')?;
+        let args: Vec<&str> = text[(open + 1)..close].split(',').map(str::trim).collect();
+        if args.len() < 3 {
+            return None;
+        }
+        let channel = | s: &str | -> Option<f32> {
+            match s.strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f32>().ok().map(| v | v / 100.),
+                None => s.parse::<f32>().ok().map(| v | v / 255.),
+            }
+        };
+        let alpha = match args.get(3) {
+            Some(a) => a.parse::<f32>().ok()?,
+            None => 1.,
+        };
+        return Some(Color::new_rgba(channel(args[0])?.clamp(0., 1.),
+                                    channel(args[1])?.clamp(0., 1.),
+                                    channel(args[2])?.clamp(0., 1.),
+                                    alpha.clamp(0., 1.)));
+    }
+
+    // TODO the rest of the named colors
+    let ret = match text {
+        "black" | "currentColor" => rgb(0, 0, 0),
+        "white" => rgb(255, 255, 255),
+        "red" => rgb(255, 0, 0),
+        "lime" => rgb(0, 255, 0),
+        "green" => rgb(0, 128, 0),
+        "blue" => rgb(0, 0, 255),
+        "yellow" => rgb(255, 255, 0),
+        "cyan" | "aqua" => rgb(0, 255, 255),
+        "magenta" | "fuchsia" => rgb(255, 0, 255),
+        "gray" | "grey" => rgb(128, 128, 128),
+        "silver" => rgb(192, 192, 192),
+        "maroon" => rgb(128, 0, 0),
+        "olive" => rgb(128, 128, 0),
+        "navy" => rgb(0, 0, 128),
+        "purple" => rgb(128, 0, 128),
+        "teal" => rgb(0, 128, 128),
+        "orange" => rgb(255, 165, 0),
+        "transparent" => Color::new_rgba(0., 0., 0., 0.),
+        _ => return None,
+    };
+    Some(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{
+        test_util::area,
+        PathCommand,
+    };
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    fn close_color(c: Color, r: f32, g: f32, b: f32, a: f32) -> bool {
+        close(c.r, r) && close(c.g, g) && close(c.b, b) && close(c.a, a)
+    }
+
+    fn parse_body(body: &str) -> Svg {
+        Svg::parse(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">{}</svg>"#,
+                            body)).unwrap()
+    }
+
+    fn positions(vertices: &[ColoredVertex2d]) -> Vec<glm::Vec2> {
+        vertices.iter().map(| v | v.position).collect()
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Svg::parse("<svg").is_err());
+        assert!(Svg::parse("<html></html>").is_err());
+    }
+
+    #[test]
+    fn view_box() {
+        let svg = Svg::parse(r#"<svg viewBox="10 10 20 10" width="40" height="40">
+            <rect x="10" y="10" width="20" height="10"/>
+        </svg>"#).unwrap();
+        assert_eq!((svg.width, svg.height), (40., 40.));
+
+        // scaled by 2 and centered vertically
+        let points = &svg.shapes[0].path.flatten(0.1)[0].points;
+        assert_eq!(points[0], glm::vec2(0., 10.));
+        assert_eq!(points[2], glm::vec2(40., 30.));
+
+        let svg = Svg::parse(r#"<svg viewBox="0 0 30 20"></svg>"#).unwrap();
+        assert_eq!((svg.width, svg.height), (30., 20.));
+
+        let svg = Svg::parse(r#"<svg viewBox="0 0 30 20" width="60"></svg>"#).unwrap();
+        assert_eq!((svg.width, svg.height), (60., 40.));
+
+        let svg = Svg::parse(r#"<svg viewBox="0 0 30 20" height="10"></svg>"#).unwrap();
+        assert_eq!((svg.width, svg.height), (15., 10.));
+
+        let svg = Svg::parse(r#"<svg width="60"></svg>"#).unwrap();
+        assert_eq!((svg.width, svg.height), (60., 0.));
+    }
+
+    #[test]
+    fn shapes_and_defaults() {
+        let svg = parse_body(r#"
+            <rect width="10" height="10"/>
+            <circle cx="5" cy="5" r="5" fill="none" stroke="red"/>
+            <g><line x1="0" y1="0" x2="10" y2="10" stroke="blue"/></g>
+            <polygon points="0,0 10,0 10,10"/>
+            <text>skipped</text>
+        "#);
+        assert_eq!(svg.shapes.len(), 4);
+
+        let rect = &svg.shapes[0];
+        assert!(close_color(rect.fill.unwrap().color, 0., 0., 0., 1.));
+        assert!(rect.stroke.is_none());
+
+        let circle = &svg.shapes[1];
+        assert!(circle.fill.is_none());
+        assert!(close_color(circle.stroke.unwrap().color, 1., 0., 0., 1.));
+        assert!(close(circle.stroke.unwrap().style.thickness, 1.));
+
+        assert!(svg.shapes[3].path.flatten(0.1)[0].closed);
+    }
+
+    #[test]
+    fn style_overrides_attributes() {
+        let svg = parse_body(r#"<rect width="1" height="1" fill="red" style="fill: blue; stroke-width: 3"
+                              stroke="lime" stroke-width="2"/>"#);
+        let shape = &svg.shapes[0];
+        assert!(close_color(shape.fill.unwrap().color, 0., 0., 1., 1.));
+        assert!(close(shape.stroke.unwrap().style.thickness, 3.));
+    }
+
+    #[test]
+    fn opacity_in_style_replaces_attribute() {
+        let svg = parse_body(r#"<rect width="1" height="1" opacity="0.5" style="opacity: 0.5"/>"#);
+        assert!(close(svg.shapes[0].fill.unwrap().color.a, 0.5));
+
+        let svg = parse_body(r#"<rect width="1" height="1" opacity="0.2" style="opacity: 0.8"/>"#);
+        assert!(close(svg.shapes[0].fill.unwrap().color.a, 0.8));
+    }
+
+    #[test]
+    fn opacity_stacks_with_parents() {
+        let svg = parse_body(r#"<g opacity="0.5" style="opacity: 0.5">
+            <rect width="1" height="1" opacity="0.5" fill-opacity="0.5"/>
+        </g>"#);
+        assert!(close(svg.shapes[0].fill.unwrap().color.a, 0.125));
+    }
+
+    #[test]
+    fn fill_rule() {
+        let svg = parse_body(r#"<path fill-rule="evenodd" d="M0 0h10v10h-10z M2 2h6v6h-6z"/>"#);
+        assert_eq!(svg.shapes[0].fill.unwrap().rule, FillRule::EvenOdd);
+
+        let mut out = Vec::new();
+        svg.tessellate(0.1, Color::white(), &mut out);
+        assert!(close(area(&positions(&out)), 64.));
+    }
+
+    #[test]
+    fn tessellate_tints() {
+        let svg = parse_body(r#"<rect width="10" height="10" fill="rgb(255, 128, 0)"/>"#);
+        let mut out = Vec::new();
+        svg.tessellate(0.1, Color::new_rgba(0.5, 1., 1., 0.5), &mut out);
+        assert!(close(area(&positions(&out)), 100.));
+        assert!(out.iter().all(| v | close_color(v.color, 0.5, 128. / 255., 0., 0.5)));
+    }
+
+    #[test]
+    fn transforms() {
+        let apply = | text: &str, x: f32, y: f32 | {
+            let v = parse_transform(text) * glm::vec3(x, y, 1.);
+            glm::vec2(v.x, v.y)
+        };
+        let near = | a: glm::Vec2, x: f32, y: f32 | close(a.x, x) && close(a.y, y);
+
+        assert!(near(apply("translate(5)", 1., 1.), 6., 1.));
+        assert!(near(apply("scale(2, 3)", 1., 1.), 2., 3.));
+        assert!(near(apply("rotate(90)", 1., 0.), 0., 1.));
+        assert!(near(apply("rotate(90 1 1)", 2., 1.), 1., 2.));
+        assert!(near(apply("matrix(1 0 0 1 3 4)", 0., 0.), 3., 4.));
+        // applied right to left
+        assert!(near(apply("translate(10, 0) scale(2)", 1., 1.), 12., 2.));
+        assert!(near(apply("translate(10, 0) bogus(2)", 1., 1.), 1., 1.));
+    }
+
+    #[test]
+    fn nested_transforms() {
+        let svg = parse_body(r#"<g transform="translate(10 0)"><rect width="1" height="1" transform="scale(2)"/></g>"#);
+        let points = &svg.shapes[0].path.flatten(0.1)[0].points;
+        assert_eq!(points[0], glm::vec2(10., 0.));
+        assert_eq!(points[2], glm::vec2(12., 2.));
+    }
+
+    #[test]
+    fn colors() {
+        assert!(close_color(parse_color("#f80").unwrap(), 1., 136. / 255., 0., 1.));
+        assert!(close_color(parse_color("#0080ff").unwrap(), 0., 128. / 255., 1., 1.));
+        assert!(close_color(parse_color("rgb(100%, 0%, 50%)").unwrap(), 1., 0., 0.5, 1.));
+        assert!(close_color(parse_color("rgba(255, 0, 0, 0.5)").unwrap(), 1., 0., 0., 0.5));
+        assert!(close_color(parse_color("navy").unwrap(), 0., 0., 128. / 255., 1.));
+        assert!(parse_color("#12").is_none());
+        assert!(parse_color("#ggg").is_none());
+        assert!(parse_color("notacolor").is_none());
+        assert_eq!(parse_paint("none").map(| p | p.is_none()), Some(true));
+    }
+
+    #[test]
+    fn path_data_commands() {
+        let path = parse_path_data("M1 1 2 2 h3 v-1 H0 V0 z m5 5 l1 0");
+        let commands = path.commands();
+        assert_eq!(commands[0], PathCommand::MoveTo(glm::vec2(1., 1.)));
+        // pairs after a move are lines
+        assert_eq!(commands[1], PathCommand::LineTo(glm::vec2(2., 2.)));
+        assert_eq!(commands[2], PathCommand::LineTo(glm::vec2(5., 2.)));
+        assert_eq!(commands[3], PathCommand::LineTo(glm::vec2(5., 1.)));
+        assert_eq!(commands[4], PathCommand::LineTo(glm::vec2(0., 1.)));
+        assert_eq!(commands[5], PathCommand::LineTo(glm::vec2(0., 0.)));
+        assert_eq!(commands[6], PathCommand::Close);
+        // relative to the start of the closed sub path
+        assert_eq!(commands[7], PathCommand::MoveTo(glm::vec2(6., 6.)));
+        assert_eq!(commands[8], PathCommand::LineTo(glm::vec2(7., 6.)));
+    }
+
+    #[test]
+    fn path_data_smooth_curves() {
+        let path = parse_path_data("M0 0 C0 1 1 1 1 0 S2 -1 2 0 Q3 1 4 0 T6 0");
+        let commands = path.commands();
+        assert_eq!(commands[2], PathCommand::CubicTo(glm::vec2(1., -1.), glm::vec2(2., -1.), glm::vec2(2., 0.)));
+        assert_eq!(commands[4], PathCommand::QuadTo(glm::vec2(5., -1.), glm::vec2(6., 0.)));
+
+        // without a previous curve the control point is the current point
+        let path = parse_path_data("M0 0 T2 0");
+        assert_eq!(path.commands()[1], PathCommand::QuadTo(glm::vec2(0., 0.), glm::vec2(2., 0.)));
+    }
+
+    #[test]
+    fn path_data_compact_numbers() {
+        // flags without separators, numbers split by signs and dots
+        let path = parse_path_data("M0,0a5,5,0,00,10,0l.5.5-1-1");
+        let commands = path.commands();
+        assert_eq!(path.current_point(), glm::vec2(9.5, -0.5));
+        match commands[commands.len() - 3] {
+            PathCommand::CubicTo(_, _, p) => assert_eq!(p, glm::vec2(10., 0.)),
+            ref other => panic!("expected arc cubic, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn path_data_stops_at_errors() {
+        let path = parse_path_data("M0 0 L1 1 L2 x L3 3");
+        assert_eq!(path.commands().len(), 2);
+        assert!(parse_path_data("").is_empty());
+        assert!(parse_path_data("L1 1").commands().len() <= 1);
+    }
+}
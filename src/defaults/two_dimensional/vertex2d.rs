@@ -1,23 +1,22 @@
-use std::mem;
-
-use memoffset::offset_of;
 use nalgebra_glm as glm;
 
 use crate::{
     gfx::{
         Mesh,
-        VertexArray,
-        VertexAttribute,
+        ObjVertex,
         Vertex,
     },
+    math::Color,
 };
 
 //
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Vertex)]
 #[repr(C)]
 pub struct Vertex2d {
+    #[location = 0]
     pub position: glm::Vec2,
+    #[location = 1]
     pub uv: glm::Vec2,
 }
 
@@ -76,27 +75,76 @@ impl Vertex2d {
     }
 }
 
-impl Vertex for Vertex2d {
-    fn set_attributes(vao: &mut VertexArray) {
-        let base = VertexAttribute {
-            size: 2,
-            ty: gl::FLOAT,
-            normalized: false,
-            stride: mem::size_of::<Self>(),
-            offset: offset_of!(Self, position),
-            divisor: 0,
-        };
-
-        // TODO enum, move these out of here?
-        vao.enable_attribute(0, VertexAttribute {
-            offset: offset_of!(Self, position),
-            .. base
-        });
-        vao.enable_attribute(1, VertexAttribute {
-            offset: offset_of!(Self, uv),
-            .. base
-        });
+/// Drops z and the normal.
+impl From<ObjVertex> for Vertex2d {
+    fn from(vert: ObjVertex) -> Self {
+        Self {
+            position: vert.position.xy(),
+            uv:       vert.uv,
+        }
     }
 }
 
 pub type Mesh2d = Mesh<Vertex2d>;
+
+/// Vertex with a color, used by the shape drawer and svgs.
+/// Needs a program that reads vertex colors, like `default_program()`.
+#[derive(Copy, Clone, Debug, Vertex)]
+#[repr(C)]
+pub struct ColoredVertex2d {
+    #[location = 0]
+    pub position: glm::Vec2,
+    #[location = 1]
+    pub uv: glm::Vec2,
+    #[location = 7]
+    pub color: Color,
+}
+
+impl ColoredVertex2d {
+    pub fn new(vert: Vertex2d, color: Color) -> Self {
+        Self {
+            position: vert.position,
+            uv:       vert.uv,
+            color,
+        }
+    }
+}
+
+/// White.
+impl From<Vertex2d> for ColoredVertex2d {
+    fn from(vert: Vertex2d) -> Self {
+        Self::new(vert, Color::white())
+    }
+}
+
+pub type ColoredMesh2d = Mesh<ColoredVertex2d>;
+
+/// Vertex drawn by the shape drawer.
+pub type ShapeVertex = ColoredVertex2d;
+
+/// Vertex of a signed distance field shape, drawn by the shape drawer.
+/// Each shape is a rounded box, drawn as one quad.
+/// Needs a program like `default_sdf_program()`.
+#[derive(Copy, Clone, Debug, Vertex)]
+#[repr(C)]
+pub struct SdfVertex {
+    #[location = 0]
+    pub position: glm::Vec2,
+    #[location = 1]
+    pub uv: glm::Vec2,
+    #[location = 7]
+    pub color: Color,
+    /// Position relative to the center of the box, in the box's frame.
+    #[location = 8]
+    pub local: glm::Vec2,
+    /// Half width, half height, corner radius, border width.
+    #[location = 9]
+    pub params: glm::Vec4,
+    #[location = 10]
+    pub border_color: Color,
+    /// Offset x, offset y in the box's frame, blur radius, unused.
+    #[location = 11]
+    pub shadow: glm::Vec4,
+    #[location = 12]
+    pub shadow_color: Color,
+}
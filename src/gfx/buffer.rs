@@ -8,12 +8,38 @@ use gl::{
     types::*,
 };
 
+use super::FrameStats;
+
+//
+
+/// OpenGL buffer usage hint.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Written once, drawn many times.
+    StaticDraw,
+    /// Written occasionally, drawn many times.
+    DynamicDraw,
+    /// Written every time it is drawn.
+    StreamDraw,
+}
+
+impl From<BufferUsage> for GLenum {
+    fn from(usage: BufferUsage) -> Self {
+        match usage {
+            BufferUsage::StaticDraw  => gl::STATIC_DRAW,
+            BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
+            BufferUsage::StreamDraw  => gl::STREAM_DRAW,
+        }
+    }
+}
+
 //
 
 /// Simple wrapper around an OpenGL buffer.
 pub struct Buffer<T> {
     buffer: GLuint,
     usage_type: GLenum,
+    len: usize,
     _phantom: PhantomData<T>,
 }
 
@@ -29,6 +55,7 @@ impl<T> Buffer<T> {
         Self {
             buffer,
             usage_type,
+            len: 0,
             _phantom: PhantomData,
         }
     }
@@ -51,6 +78,26 @@ impl<T> Buffer<T> {
         ret
     }
 
+    /// Creates a new immutable buffer of size `len`, using `glBufferStorage`.
+    /// note: `buffer_null()` and `buffer_data()` cannot be used on this buffer
+    pub fn storage(len: usize, flags: GLbitfield) -> Self {
+        use std::ptr;
+
+        let mut ret = unsafe {
+            Self::new(gl::STREAM_DRAW)
+        };
+        ret.len = len;
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, ret.buffer);
+            gl::BufferStorage(gl::ARRAY_BUFFER,
+                (len * mem::size_of::<T>()) as GLsizeiptr,
+                ptr::null(),
+                flags);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        ret
+    }
+
     /// Reinitializes buffer to size `len`.
     pub fn buffer_null(&mut self, len: usize) {
         use std::ptr;
@@ -63,6 +110,7 @@ impl<T> Buffer<T> {
                 self.usage_type);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
+        self.len = len;
     }
 
     /// Reinitializes buffer from a slice.
@@ -77,29 +125,65 @@ impl<T> Buffer<T> {
                 self.usage_type);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
+        self.len = data.len();
+        FrameStats::record_upload(mem::size_of_val(data));
     }
 
     /// Subs data into buffer from a slice.
-    // TODO check buffer size before doing this
-    //        have to keep in struct
-    //      check what opengl does if writing something too big
+    /// `offset` is in bytes.
+    /// Will panic if the data does not fit in the buffer.
     pub fn buffer_sub_data(&self, offset: usize, data: &[T]) {
         if data.len() == 0 {
             return;
         }
+        let size = mem::size_of_val(data);
+        let buffer_size = self.len * mem::size_of::<T>();
+        assert!(offset + size <= buffer_size,
+                "buffer sub data out of range: bytes {}..{} > {}",
+                offset, offset + size, buffer_size);
         unsafe {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
             gl::BufferSubData(gl::ARRAY_BUFFER,
                 offset as GLintptr,
-                (data.len() * mem::size_of::<T>()) as GLsizeiptr,
+                size as GLsizeiptr,
                 data.as_ptr() as _);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
+        FrameStats::record_upload(size);
+    }
+
+    /// Subs data into buffer from a slice, starting at element `offset_elements`.
+    /// Will panic if the data does not fit in the buffer.
+    pub fn buffer_sub_data_at(&self, offset_elements: usize, data: &[T]) {
+        self.buffer_sub_data(offset_elements * mem::size_of::<T>(), data);
+    }
+
+    // TODO could use some other type that automatically unmaps when leaving scope
+    //        like mutex lock
+
+    /// Maps `len` elements of the buffer starting at `offset`.
+    /// Returns null if the mapping failed.
+    pub fn map_range(&self, offset: usize, len: usize, access: GLbitfield) -> *mut T {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+            let ret = gl::MapBufferRange(gl::ARRAY_BUFFER,
+                (offset * mem::size_of::<T>()) as GLintptr,
+                (len * mem::size_of::<T>()) as GLsizeiptr,
+                access);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            ret as _
+        }
     }
 
-    // TODO map buffer
-    //   could use some other type that automatically unmaps when leaving scope
-    //   like mutex lock
+    /// Unmaps the buffer.
+    /// note: not neccessary for persistently mapped buffers
+    pub fn unmap(&self) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+            gl::UnmapBuffer(gl::ARRAY_BUFFER);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
 
     pub fn bind_to(&self, target: GLenum) {
         unsafe {
@@ -113,6 +197,15 @@ impl<T> Buffer<T> {
         }
     }
 
+    /// Length of the buffer in elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn gl(&self) -> GLuint {
         self.buffer
     }
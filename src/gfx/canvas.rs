@@ -7,7 +7,11 @@ use image::{
     RgbaImage,
 };
 
-use super::Texture;
+use super::{
+    clear_framebuffer,
+    ClearOptions,
+    Texture,
+};
 
 //
 
@@ -70,6 +74,11 @@ impl Canvas {
         }
     }
 
+    /// Does not change which framebuffer is bound.
+    pub fn clear(&self, options: &ClearOptions) {
+        clear_framebuffer(self.fbo, options);
+    }
+
     pub fn texture(&self) -> &Texture {
         &self.texture
     }
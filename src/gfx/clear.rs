@@ -0,0 +1,77 @@
+use gl::{
+    self,
+    types::*,
+};
+
+use crate::math::Color;
+
+//
+
+/// What to clear and what to clear it to.
+/// `None` leaves that buffer untouched.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ClearOptions {
+    pub color: Option<Color>,
+    pub depth: Option<f32>,
+    pub stencil: Option<i32>,
+}
+
+impl ClearOptions {
+    /// Clears color to `color`, depth to 1 and stencil to 0.
+    pub fn all(color: Color) -> Self {
+        Self {
+            color: Some(color),
+            depth: Some(1.),
+            stencil: Some(0),
+        }
+    }
+
+    pub fn color(color: Color) -> Self {
+        Self {
+            color: Some(color),
+            .. Default::default()
+        }
+    }
+}
+
+/// Clears the currently bound framebuffer.
+/// note: respects the active scissor rect and write masks of the current `RenderState`
+pub fn clear(options: &ClearOptions) {
+    let mut mask = 0;
+    unsafe {
+        if let Some(color) = options.color {
+            gl::ClearColor(color.r, color.g, color.b, color.a);
+            mask |= gl::COLOR_BUFFER_BIT;
+        }
+        if let Some(depth) = options.depth {
+            gl::ClearDepth(depth as GLdouble);
+            mask |= gl::DEPTH_BUFFER_BIT;
+        }
+        if let Some(stencil) = options.stencil {
+            gl::ClearStencil(stencil);
+            mask |= gl::STENCIL_BUFFER_BIT;
+        }
+        if mask != 0 {
+            gl::Clear(mask);
+        }
+    }
+}
+
+/// Clears a framebuffer, then restores the previous binding.
+pub(crate) fn clear_framebuffer(fbo: GLuint, options: &ClearOptions) {
+    let mut prev = 0;
+    unsafe {
+        gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut prev);
+        if prev as GLuint != fbo {
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, fbo);
+        }
+    }
+
+    clear(options);
+
+    unsafe {
+        if prev as GLuint != fbo {
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, prev as GLuint);
+        }
+    }
+}
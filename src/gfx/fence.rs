@@ -0,0 +1,84 @@
+use gl::{
+    self,
+    types::*,
+};
+
+//
+
+/// Simple wrapper around an OpenGL sync object.
+/// Signaled once every command issued before it has completed on the GPU.
+pub struct Fence {
+    sync: GLsync,
+}
+
+impl Fence {
+    /// Inserts a new fence into the command stream.
+    pub fn new() -> Self {
+        let sync = unsafe {
+            gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)
+        };
+        Self {
+            sync,
+        }
+    }
+
+    /// Checks if the fence has been signaled without blocking.
+    pub fn is_signaled(&self) -> bool {
+        let status = unsafe {
+            gl::ClientWaitSync(self.sync, 0, 0)
+        };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    /// Blocks until the fence has been signaled.
+    /// Errors if the wait failed, in which case the fence may not have been signaled.
+    pub fn wait(&self) -> Result<(), String> {
+        // 1ms
+        const TIMEOUT: GLuint64 = 1_000_000;
+
+        loop {
+            let status = unsafe {
+                gl::ClientWaitSync(self.sync, gl::SYNC_FLUSH_COMMANDS_BIT, TIMEOUT)
+            };
+            match status {
+                gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => return Ok(()),
+                gl::WAIT_FAILED => {
+                    let error = unsafe {
+                        gl::GetError()
+                    };
+                    return Err(format!("fence wait failed: gl error {:#x}", error));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Blocks until the fence has been signaled.
+    /// If the wait fails, blocks until every command has completed with `glFinish` instead,
+    ///   so the GPU is always done with what the fence guards when this returns.
+    pub fn wait_or_finish(&self) {
+        if self.wait().is_err() {
+            unsafe {
+                gl::Finish();
+            }
+        }
+    }
+
+    pub fn gl(&self) -> GLsync {
+        self.sync
+    }
+}
+
+impl Default for Fence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSync(self.sync);
+        }
+    }
+}
@@ -1,39 +1,174 @@
+use std::mem;
+
 use super::{
+    gl_supports,
     Vertex,
-    Buffer,
+    RingBuffer,
     Mesh,
 };
 
 //
 
-/// Optimized instancer of a mesh.
-pub struct Instancer<T: Vertex> {
-    ibo: Buffer<T>,
+/// Handle to a mesh registered in an `Instancer`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MeshId(usize);
+
+/// Fixed capacity list of instances, flushed whenever it fills up.
+/// Kept apart from the GL side of `Instancer` so the bookkeeping can be tested on its own.
+struct InstanceBatch<T> {
     vec: Vec<T>,
+    capacity: usize,
+}
+
+impl<T> InstanceBatch<T> {
+    fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "instancer capacity must be at least 1");
+        Self {
+            vec: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    fn empty_count(&self) -> usize {
+        self.capacity - self.vec.len()
+    }
+
+    fn clear(&mut self) {
+        self.vec.clear();
+    }
+
+    /// Calls `flush` with everything batched, if anything is, then clears.
+    fn flush<F: FnMut(&[T])>(&mut self, mut flush: F) {
+        if !self.vec.is_empty() {
+            flush(&self.vec);
+            self.vec.clear();
+        }
+    }
+
+    /// Makes room for one more instance, flushing if full.
+    fn reserve_one<F: FnMut(&[T])>(&mut self, flush: F) {
+        if self.empty_count() == 0 {
+            self.flush(flush);
+        }
+    }
+
+    fn push<F: FnMut(&[T])>(&mut self, obj: T, flush: F) -> &mut T {
+        self.reserve_one(flush);
+        self.vec.push(obj);
+        self.vec.last_mut().unwrap()
+    }
+
+    /// # Safety
+    ///
+    /// See `BoundInstancer::pull()`.
+    unsafe fn pull<F: FnMut(&[T])>(&mut self, flush: F) -> &mut T {
+        self.reserve_one(flush);
+        let len = self.vec.len();
+        self.vec.set_len(len + 1);
+        self.vec.get_unchecked_mut(len)
+    }
+}
+
+impl<T: Clone> InstanceBatch<T> {
+    fn push_slice<F: FnMut(&[T])>(&mut self, objs: &[T], mut flush: F) {
+        let mut rest = objs;
+        while !rest.is_empty() {
+            self.reserve_one(&mut flush);
+            let ct = self.empty_count().min(rest.len());
+            self.vec.extend_from_slice(&rest[..ct]);
+            rest = &rest[ct..];
+        }
+    }
+}
+
+//
+
+/// Points the instance attributes of `mesh` at `offset` elements into the ring buffer.
+fn set_instance_attributes<T: Vertex, M: Vertex>(ibo: &RingBuffer<T>,
+                                                 mesh: &mut Mesh<M>,
+                                                 offset: usize
+) -> Result<(), String> {
+    let vao = mesh.vao_mut();
+    vao.bind();
+    ibo.buffer().bind_to(gl::ARRAY_BUFFER);
+    vao.set_base_offset(offset * mem::size_of::<T>());
+    let ret = T::set_attributes(vao);
+    vao.set_base_offset(0);
+    vao.unbind();
+    ret
 }
 
-impl<T: Vertex> Instancer<T> {
+/// Streams `objs` into the ring buffer and draws them instanced with `mesh`.
+/// With `base_instance`, the attributes stay at the start of the buffer
+///   and the draw starts at the region just written,
+///   otherwise the attributes are pointed at the region.
+fn draw_instances<T: Vertex, M: Vertex>(ibo: &mut RingBuffer<T>,
+                                        mesh: &mut Mesh<M>,
+                                        objs: &[T],
+                                        base_instance: bool) {
+    let offset = ibo.push_slice(objs);
+
+    if base_instance {
+        mesh.draw_instanced_base_instance(objs.len(), offset);
+    } else {
+        set_instance_attributes(ibo, mesh, offset)
+            .expect("instance attributes are checked by register_mesh()");
+        mesh.draw_instanced(objs.len());
+    }
+}
+
+/// Optimized instancer of meshes.
+/// Instance data is streamed through a `RingBuffer`,
+///   so many draws can be issued per frame without waiting on the GPU.
+/// The same instance stream can be drawn with any of the registered meshes.
+pub struct Instancer<T: Vertex, M: Vertex> {
+    ibo: RingBuffer<T>,
+    batch: InstanceBatch<T>,
+    meshes: Vec<Mesh<M>>,
+    base_instance: bool,
+}
+
+impl<T: Vertex, M: Vertex> Instancer<T, M> {
+    /// Number of draws of `size` instances that can be in flight at once.
+    const SECTION_COUNT: usize = 3;
+
     pub fn new(size: usize) -> Self {
-        let ibo = Buffer::empty(size, gl::STREAM_DRAW);
-        let vec = Vec::with_capacity(size);
+        let ibo = RingBuffer::new(size, Self::SECTION_COUNT);
+        let batch = InstanceBatch::new(size);
 
         Self {
             ibo,
-            vec,
+            batch,
+            meshes: Vec::new(),
+            base_instance: gl_supports(4, 2, "GL_ARB_base_instance"),
         }
     }
 
-    pub fn make_mesh_compatible<M: Vertex>(&self, mesh: &mut Mesh<M>) {
-        let vao = mesh.vao_mut();
+    /// Takes ownership of a mesh so it can be drawn instanced.
+    /// Errors if the instance attributes of `T` aren't supported by the context.
+    pub fn register_mesh(&mut self, mut mesh: Mesh<M>) -> Result<MeshId, String> {
+        set_instance_attributes(&self.ibo, &mut mesh, 0)?;
+        self.meshes.push(mesh);
+        Ok(MeshId(self.meshes.len() - 1))
+    }
+
+    /// Will panic if the id is not from this instancer.
+    pub fn mesh(&self, id: MeshId) -> &Mesh<M> {
+        &self.meshes[id.0]
+    }
 
-        vao.bind();
-        self.ibo.bind_to(gl::ARRAY_BUFFER);
-        T::set_attributes(vao);
-        vao.unbind();
+    /// Will panic if the id is not from this instancer.
+    pub fn mesh_mut(&mut self, id: MeshId) -> &mut Mesh<M> {
+        &mut self.meshes[id.0]
     }
 
     /// note: does not change OpenGL state to bind
-    pub fn bind<'a, M: Vertex>(&'a mut self, mesh: &'a Mesh<M>) -> BoundInstancer<'a, T, M> {
+    pub fn bind(&mut self, mesh: MeshId) -> BoundInstancer<'_, T, M> {
+        assert!(mesh.0 < self.meshes.len(), "invalid mesh id");
         let mut ret = BoundInstancer {
             base: self,
             mesh,
@@ -43,75 +178,110 @@ impl<T: Vertex> Instancer<T> {
     }
 
     pub fn fill_count(&self) -> usize {
-        self.vec.len()
+        self.batch.len()
     }
 
     pub fn empty_count(&self) -> usize {
-        self.vec.capacity() - self.vec.len()
+        self.batch.empty_count()
     }
 }
 
 pub struct BoundInstancer<'a, T: Vertex, M: Vertex> {
-    base: &'a mut Instancer<T>,
-    mesh: &'a Mesh<M>,
+    base: &'a mut Instancer<T, M>,
+    mesh: MeshId,
 }
 
 impl<'a, T: Vertex, M: Vertex> BoundInstancer<'a, T, M> {
     /// note: does not change OpenGL state
     fn begin(&mut self) {
-        self.base.vec.clear();
+        self.base.batch.clear();
     }
 
     fn end(&mut self) {
-        if self.base.fill_count() > 0 {
+        self.draw();
+    }
+
+    /// Switches the mesh instances are drawn with.
+    /// Draws anything pushed with the previous mesh first.
+    pub fn set_mesh(&mut self, mesh: MeshId) {
+        assert!(mesh.0 < self.base.meshes.len(), "invalid mesh id");
+        if mesh != self.mesh {
             self.draw();
+            self.mesh = mesh;
         }
     }
 
+    pub fn mesh(&self) -> MeshId {
+        self.mesh
+    }
+
     /// note: not expensive to call if instancer is empty
     pub fn draw(&mut self) {
-        if self.base.fill_count() > 0 {
-            self.base.ibo.buffer_sub_data(0, &self.base.vec);
-            self.mesh.draw_instanced(self.base.fill_count());
-            self.clear();
-        }
+        let Instancer { ibo, batch, meshes, base_instance } = &mut *self.base;
+        let mesh = &mut meshes[self.mesh.0];
+        batch.flush(| objs | draw_instances(ibo, mesh, objs, *base_instance));
     }
 
     pub fn clear(&mut self) {
-        self.base.vec.clear();
+        self.base.batch.clear();
     }
 
+    /// note: if the instancer is full, a draw call will happen before `obj` is added
     pub fn push(&mut self, obj: T) {
-        if self.base.empty_count() == 0 {
-            self.draw();
-        }
-        self.base.vec.push(obj);
+        self.pull_with(|| obj);
+    }
+
+    /// Pushes the result of `f` and returns an &mut T to it for the caller to modify.
+    /// note: if the instancer is full, a draw call will happen before the new T is added,
+    ///         so the returned T is always part of the next draw
+    pub fn pull_with<F: FnOnce() -> T>(&mut self, f: F) -> &mut T {
+        let Instancer { ibo, batch, meshes, base_instance } = &mut *self.base;
+        let mesh = &mut meshes[self.mesh.0];
+        batch.push(f(), | objs | draw_instances(ibo, mesh, objs, *base_instance))
     }
 
     /// Returns an &mut T for the caller to override.
     /// Will be uninitialized.
-    /// note: draw call may happen such that this &mut T will not be included in it
-    pub fn pull(&mut self) -> &mut T {
-        if self.base.empty_count() == 0 {
-            self.draw();
-        }
-        let len = self.base.vec.len();
-        let ret = unsafe {
-            self.base.vec.set_len(len + 1);
-            self.base.vec.get_unchecked_mut(len)
-        };
-        ret
+    /// note: if the instancer is full, a draw call will happen before the new T is added,
+    ///         so the returned T is always part of the next draw
+    ///
+    /// # Safety
+    ///
+    /// Every field of the returned T must be written before it is read or drawn,
+    ///   and T must not have any invalid bit patterns or drop glue.
+    /// Prefer `pull_with()` or `pull_default()`.
+    pub unsafe fn pull(&mut self) -> &mut T {
+        let Instancer { ibo, batch, meshes, base_instance } = &mut *self.base;
+        let mesh = &mut meshes[self.mesh.0];
+        batch.pull(| objs | draw_instances(ibo, mesh, objs, *base_instance))
+    }
+}
+
+impl<'a, T: Vertex + Clone, M: Vertex> BoundInstancer<'a, T, M> {
+    /// Pushes every element of a slice,
+    ///   drawing each time the instancer fills up.
+    pub fn push_slice(&mut self, objs: &[T]) {
+        let Instancer { ibo, batch, meshes, base_instance } = &mut *self.base;
+        let mesh = &mut meshes[self.mesh.0];
+        batch.push_slice(objs, | objs | draw_instances(ibo, mesh, objs, *base_instance));
     }
 }
 
 impl<'a, T: Vertex + Default, M: Vertex> BoundInstancer<'a, T, M> {
     /// Returns an &mut T for the caller to override.
-    /// Will be initialized with `DEfault::default()`.
-    /// note: draw call may happen such that this &mut T will not be included in it
+    /// Will be initialized with `Default::default()`.
+    /// note: if the instancer is full, a draw call will happen before the new T is added,
+    ///         so the returned T is always part of the next draw
     pub fn pull_default(&mut self) -> &mut T {
-        let ret = self.pull();
-        *ret = Default::default();
-        ret
+        self.pull_with(Default::default)
+    }
+}
+
+impl<'a, T: Vertex, M: Vertex> Extend<T> for BoundInstancer<'a, T, M> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for obj in iter {
+            self.push(obj);
+        }
     }
 }
 
@@ -120,3 +290,72 @@ impl<'a, T: Vertex, M: Vertex> Drop for BoundInstancer<'a, T, M> {
         self.end();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_flushes_only_when_full() {
+        let mut batch = InstanceBatch::new(3);
+        let mut flushed = Vec::new();
+
+        for i in 0..3 {
+            batch.push(i, | objs: &[i32] | flushed.push(objs.to_vec()));
+        }
+        assert!(flushed.is_empty());
+        assert_eq!(batch.empty_count(), 0);
+
+        // the 4th push flushes the full batch first, then is kept for the next flush
+        *batch.push(3, | objs: &[i32] | flushed.push(objs.to_vec())) += 10;
+        assert_eq!(flushed, vec![vec![0, 1, 2]]);
+        assert_eq!(batch.len(), 1);
+
+        batch.flush(| objs | flushed.push(objs.to_vec()));
+        assert_eq!(flushed, vec![vec![0, 1, 2], vec![13]]);
+        assert_eq!(batch.len(), 0);
+    }
+
+    #[test]
+    fn flush_skips_empty() {
+        let mut batch = InstanceBatch::<i32>::new(2);
+        let mut calls = 0;
+        batch.flush(| _ | calls += 1);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn push_slice_chunks_at_capacity() {
+        let mut batch = InstanceBatch::new(4);
+        let mut flushed = Vec::new();
+
+        batch.push(0, | objs: &[i32] | flushed.push(objs.to_vec()));
+        let objs: Vec<i32> = (1..11).collect();
+        batch.push_slice(&objs, | objs | flushed.push(objs.to_vec()));
+        assert_eq!(flushed, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]);
+        assert_eq!(batch.len(), 3);
+
+        batch.flush(| objs | flushed.push(objs.to_vec()));
+        assert_eq!(flushed[2], vec![8, 9, 10]);
+    }
+
+    #[test]
+    fn push_slice_exact_capacity_does_not_flush() {
+        let mut batch = InstanceBatch::new(4);
+        let mut calls = 0;
+        batch.push_slice(&[1, 2, 3, 4], | _ | calls += 1);
+        assert_eq!(calls, 0);
+        assert_eq!(batch.empty_count(), 0);
+
+        batch.push_slice(&[5], | _ | calls += 1);
+        assert_eq!(calls, 1);
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[test]
+    fn capacity_is_exact() {
+        // Vec::with_capacity may allocate more, the batch must not use it
+        let batch = InstanceBatch::<u8>::new(5);
+        assert_eq!(batch.empty_count(), 5);
+    }
+}
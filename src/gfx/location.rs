@@ -6,6 +6,7 @@ use gl::{
 use super::{
     Program,
     Uniform,
+    FrameStats,
 };
 
 //
@@ -38,5 +39,8 @@ impl Location {
 
     pub fn set<T: Uniform>(&self, val: &T) {
         val.uniform(self);
+        if val.counts_as_upload() {
+            FrameStats::record(| s | s.uniform_uploads += 1);
+        }
     }
 }
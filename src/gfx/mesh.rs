@@ -1,4 +1,10 @@
-use std::ptr;
+use std::{
+    mem,
+    ops::{
+        Deref,
+        Range,
+    },
+};
 
 use gl::{
     self,
@@ -9,99 +15,502 @@ use super::{
     VertexArray,
     Vertex,
     Buffer,
+    BufferUsage,
+    FrameStats,
 };
 
 //
 
-pub struct Mesh<T: Vertex> {
+/// Range of elements that have changed since the last upload.
+#[derive(Clone, Debug, Default)]
+struct DirtyRange {
+    range: Option<Range<usize>>,
+}
+
+impl DirtyRange {
+    fn mark(&mut self, range: Range<usize>) {
+        if range.start >= range.end {
+            return;
+        }
+        self.range = Some(match self.range.take() {
+            Some(r) => r.start.min(range.start)..r.end.max(range.end),
+            None => range,
+        });
+    }
+
+    fn take(&mut self) -> Option<Range<usize>> {
+        self.range.take()
+    }
+}
+
+/// What has to be uploaded to bring a buffer up to date with its vec.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Upload {
+    Nothing,
+    /// The vec has outgrown the buffer, it has to be resized and everything uploaded.
+    All,
+    Range(Range<usize>),
+}
+
+impl Upload {
+    fn plan(buffer_len: usize, vec_len: usize, dirty: Option<Range<usize>>) -> Self {
+        if vec_len > buffer_len {
+            return Upload::All;
+        }
+        match dirty {
+            // the vec may have been truncated since the range was marked
+            Some(range) if range.start < range.end.min(vec_len) => {
+                Upload::Range(range.start..range.end.min(vec_len))
+            }
+            _ => Upload::Nothing,
+        }
+    }
+}
+
+/// Mutable access to the vertices or indices of a `Mesh`.
+/// Keeps track of what was changed, so `Mesh::sync()` only uploads that.
+pub struct MeshDataMut<'a, T> {
+    vec: &'a mut Vec<T>,
+    dirty: &'a mut DirtyRange,
+    whole_vec: bool,
+}
+
+impl<'a, T> MeshDataMut<'a, T> {
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        if idx < self.vec.len() {
+            self.dirty.mark(idx..(idx + 1));
+        }
+        self.vec.get_mut(idx)
+    }
+
+    /// Will panic if the range is out of bounds.
+    pub fn range_mut(&mut self, range: Range<usize>) -> &mut [T] {
+        self.dirty.mark(range.clone());
+        &mut self.vec[range]
+    }
+
+    pub fn push(&mut self, obj: T) {
+        let len = self.vec.len();
+        self.dirty.mark(len..(len + 1));
+        self.vec.push(obj);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.vec.pop()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.vec.truncate(len);
+    }
+
+    pub fn clear(&mut self) {
+        self.vec.clear();
+    }
+
+    /// Marks everything as changed.
+    pub fn as_mut_vec(&mut self) -> &mut Vec<T> {
+        self.whole_vec = true;
+        self.vec
+    }
+}
+
+impl<'a, T> Extend<T> for MeshDataMut<'a, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let start = self.vec.len();
+        self.vec.extend(iter);
+        let end = self.vec.len();
+        self.dirty.mark(start..end);
+    }
+}
+
+impl<'a, T> Deref for MeshDataMut<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.vec
+    }
+}
+
+impl<'a, T> Drop for MeshDataMut<'a, T> {
+    fn drop(&mut self) {
+        if self.whole_vec {
+            self.dirty.mark(0..self.vec.len());
+        }
+    }
+}
+
+//
+
+/// OpenGL primitive type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl From<Primitive> for GLenum {
+    fn from(primitive: Primitive) -> Self {
+        match primitive {
+            Primitive::Points        => gl::POINTS,
+            Primitive::Lines         => gl::LINES,
+            Primitive::LineStrip     => gl::LINE_STRIP,
+            Primitive::LineLoop      => gl::LINE_LOOP,
+            Primitive::Triangles     => gl::TRIANGLES,
+            Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+            Primitive::TriangleFan   => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+/// Types that can be used as mesh indices.
+pub trait Index: Copy {
+    const GL_TYPE: GLenum;
+
+    /// Index that restarts the primitive when primitive restart is enabled.
+    const RESTART: Self;
+
+    fn restart_index() -> GLuint;
+}
+
+impl Index for u8 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_BYTE;
+    const RESTART: Self = u8::MAX;
+
+    fn restart_index() -> GLuint {
+        Self::RESTART as GLuint
+    }
+}
+
+impl Index for u16 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_SHORT;
+    const RESTART: Self = u16::MAX;
+
+    fn restart_index() -> GLuint {
+        Self::RESTART as GLuint
+    }
+}
+
+impl Index for u32 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_INT;
+    const RESTART: Self = u32::MAX;
+
+    fn restart_index() -> GLuint {
+        Self::RESTART
+    }
+}
+
+//
+
+/// A vertex array with vertices and optional indices.
+/// If there are no indices, vertices are drawn in order.
+pub struct Mesh<T: Vertex, I: Index = u32> {
     vao: VertexArray,
     vbo: Buffer<T>,
     vertices: Vec<T>,
-    ebo: Buffer<u32>,
-    indices: Vec<u32>,
-    _buffer_type: GLenum,
-    draw_type: GLenum,
+    vertices_dirty: DirtyRange,
+    vertices_uploaded: usize,
+    ebo: Buffer<I>,
+    indices: Vec<I>,
+    indices_dirty: DirtyRange,
+    indices_uploaded: usize,
+    primitive: Primitive,
+    primitive_restart: bool,
 }
 
-impl<T: Vertex> Mesh<T> {
-    pub fn new(vertices: Vec<T>, indices: Vec<u32>, buffer_type: GLenum, draw_type: GLenum) -> Self {
+impl<T: Vertex, I: Index> Mesh<T, I> {
+    /// Errors if the vertex attributes of `T` aren't supported by the context.
+    pub fn new(vertices: Vec<T>,
+               indices: Vec<I>,
+               usage: BufferUsage,
+               primitive: Primitive
+    ) -> Result<Self, String> {
         let mut vao = VertexArray::new();
-        let vbo = Buffer::from_slice(&vertices, buffer_type);
-        let ebo = Buffer::from_slice(&indices, buffer_type);
+        let vbo = Buffer::from_slice(&vertices, usage.into());
+        let ebo = Buffer::from_slice(&indices, usage.into());
 
         vao.bind();
         vbo.bind_to(gl::ARRAY_BUFFER);
-        T::set_attributes(&mut vao);
+        let attributes = T::set_attributes(&mut vao);
         ebo.bind_to(gl::ELEMENT_ARRAY_BUFFER);
         vao.unbind();
+        attributes?;
 
-        Self {
+        Ok(Self {
             vao,
             vbo,
+            vertices_uploaded: vertices.len(),
             vertices,
+            vertices_dirty: DirtyRange::default(),
             ebo,
+            indices_uploaded: indices.len(),
             indices,
-            _buffer_type: buffer_type,
-            draw_type,
-        }
+            indices_dirty: DirtyRange::default(),
+            primitive,
+            primitive_restart: false,
+        })
     }
 
-    pub fn draw(&self) {
-        let i_ct = self.indices.len();
-
-        self.vao.bind();
-        if i_ct == 0 {
-            unsafe {
-                gl::DrawArrays(self.draw_type,
-                               0,
-                               self.vertices.len() as GLint);
-            }
+    /// Number of indices, or vertices if there are no indices.
+    /// note: only counts what has been uploaded,
+    ///         edits are not drawn until `sync()` or `buffer_data()`
+    pub fn draw_count(&self) -> usize {
+        if self.indices_uploaded == 0 {
+            self.vertices_uploaded
         } else {
-            unsafe {
-                gl::DrawElements(self.draw_type,
-                                 i_ct as GLint,
-                                 gl::UNSIGNED_INT,
-                                 ptr::null());
-            }
+            self.indices_uploaded
         }
-        self.vao.unbind();
+    }
+
+    pub fn draw(&self) {
+        self.draw_impl(0..self.draw_count(), 0, None, 0);
     }
 
     pub fn draw_instanced(&self, n: usize) {
-        let i_ct = self.indices.len();
+        self.draw_impl(0..self.draw_count(), 0, Some(n), 0);
+    }
+
+    /// Draws instanced, with instanced attributes starting from instance `base_instance`.
+    /// Lets instance data for several draws live in one buffer without re-specifying attributes.
+    /// note: needs GL 4.2 or `GL_ARB_base_instance`
+    pub fn draw_instanced_base_instance(&self, n: usize, base_instance: usize) {
+        self.draw_impl(0..self.draw_count(), 0, Some(n), base_instance);
+    }
+
+    /// Draws a range of indices, or vertices if there are no indices.
+    /// Will panic if the range ends past `draw_count()`.
+    pub fn draw_range(&self, range: Range<usize>) {
+        self.draw_impl(range, 0, None, 0);
+    }
+
+    pub fn draw_range_instanced(&self, range: Range<usize>, n: usize) {
+        self.draw_impl(range, 0, Some(n), 0);
+    }
+
+    /// Draws a range of indices, with `base_vertex` added to each index.
+    /// Lets several shapes live in one mesh, each with indices starting at 0.
+    pub fn draw_range_base_vertex(&self, range: Range<usize>, base_vertex: i32) {
+        self.draw_impl(range, base_vertex, None, 0);
+    }
+
+    pub fn draw_range_base_vertex_instanced(&self, range: Range<usize>, base_vertex: i32, n: usize) {
+        self.draw_impl(range, base_vertex, Some(n), 0);
+    }
+
+    fn draw_impl(&self,
+                 range: Range<usize>,
+                 base_vertex: i32,
+                 instances: Option<usize>,
+                 base_instance: usize) {
+        assert!(range.end <= self.draw_count(),
+                "draw range out of bounds: {:?} > {}", range, self.draw_count());
+        if range.start >= range.end {
+            return;
+        }
+
+        let mode: GLenum = self.primitive.into();
+        let count = (range.end - range.start) as GLsizei;
 
         self.vao.bind();
-        if i_ct == 0 {
-            unsafe {
-                gl::DrawArraysInstanced(self.draw_type,
-                                        0,
-                                        self.vertices.len() as GLint,
-                                        n as GLint);
+        unsafe {
+            if self.primitive_restart {
+                gl::Enable(gl::PRIMITIVE_RESTART);
+                gl::PrimitiveRestartIndex(I::restart_index());
             }
-        } else {
-            unsafe {
-                gl::DrawElementsInstanced(self.draw_type,
-                                          i_ct as GLint,
-                                          gl::UNSIGNED_INT,
-                                          ptr::null(),
-                                          n as GLint);
+
+            if self.indices.is_empty() {
+                let first = range.start as GLint + base_vertex;
+                match (instances, base_instance) {
+                    (None, _) => gl::DrawArrays(mode, first, count),
+                    (Some(n), 0) => gl::DrawArraysInstanced(mode, first, count, n as GLsizei),
+                    (Some(n), bi) => {
+                        gl::DrawArraysInstancedBaseInstance(mode, first, count, n as GLsizei, bi as GLuint);
+                    }
+                }
+            } else {
+                let offset = (range.start * mem::size_of::<I>()) as *const _;
+                match (instances, base_vertex, base_instance) {
+                    (None, 0, _) => {
+                        gl::DrawElements(mode, count, I::GL_TYPE, offset);
+                    }
+                    (None, bv, _) => {
+                        gl::DrawElementsBaseVertex(mode, count, I::GL_TYPE, offset, bv);
+                    }
+                    (Some(n), 0, 0) => {
+                        gl::DrawElementsInstanced(mode, count, I::GL_TYPE, offset, n as GLsizei);
+                    }
+                    (Some(n), bv, 0) => {
+                        gl::DrawElementsInstancedBaseVertex(mode, count, I::GL_TYPE, offset, n as GLsizei, bv);
+                    }
+                    (Some(n), 0, bi) => {
+                        gl::DrawElementsInstancedBaseInstance(mode, count, I::GL_TYPE, offset,
+                                                              n as GLsizei, bi as GLuint);
+                    }
+                    (Some(n), bv, bi) => {
+                        gl::DrawElementsInstancedBaseVertexBaseInstance(mode, count, I::GL_TYPE, offset,
+                                                                        n as GLsizei, bv, bi as GLuint);
+                    }
+                }
+            }
+
+            if self.primitive_restart {
+                gl::Disable(gl::PRIMITIVE_RESTART);
             }
         }
         self.vao.unbind();
+
+        FrameStats::record_draw(count as usize, instances.unwrap_or(1));
     }
 
+    /// When enabled, `I::RESTART` in the indices starts a new primitive.
+    /// Useful for strips and fans.
+    pub fn set_primitive_restart(&mut self, enabled: bool) {
+        self.primitive_restart = enabled;
+    }
+
+    pub fn primitive(&self) -> Primitive {
+        self.primitive
+    }
+
+    pub fn set_primitive(&mut self, primitive: Primitive) {
+        self.primitive = primitive;
+    }
+
+    /// Reuploads all vertices and indices.
     pub fn buffer_data(&mut self) {
         self.vbo.buffer_data(&self.vertices);
         self.ebo.buffer_data(&self.indices);
+        self.vertices_dirty.take();
+        self.indices_dirty.take();
+        self.vertices_uploaded = self.vertices.len();
+        self.indices_uploaded = self.indices.len();
     }
 
-    // TODO
-    // pub fn vertices_mut(&mut self) -> &mut Vertices {
-        // &mut self.vertices
-    // }
+    /// Uploads only the vertices and indices that have changed.
+    /// If they have grown past the size of the GPU buffers, the buffers are resized.
+    /// note: should be called before drawing if the mesh was edited
+    pub fn sync(&mut self) {
+        Self::sync_buffer(&mut self.vbo, &self.vertices, &mut self.vertices_dirty);
+        Self::sync_buffer(&mut self.ebo, &self.indices, &mut self.indices_dirty);
+        self.vertices_uploaded = self.vertices.len();
+        self.indices_uploaded = self.indices.len();
+    }
+
+    fn sync_buffer<E>(buffer: &mut Buffer<E>, vec: &Vec<E>, dirty: &mut DirtyRange) {
+        match Upload::plan(buffer.len(), vec.len(), dirty.take()) {
+            Upload::Nothing => {}
+            Upload::All => {
+                buffer.buffer_null(vec.capacity());
+                buffer.buffer_sub_data_at(0, vec);
+            }
+            Upload::Range(range) => {
+                buffer.buffer_sub_data_at(range.start, &vec[range]);
+            }
+        }
+    }
+
+    pub fn vertices(&self) -> &[T] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[I] {
+        &self.indices
+    }
+
+    pub fn vertices_mut(&mut self) -> MeshDataMut<'_, T> {
+        MeshDataMut {
+            vec: &mut self.vertices,
+            dirty: &mut self.vertices_dirty,
+            whole_vec: false,
+        }
+    }
+
+    pub fn indices_mut(&mut self) -> MeshDataMut<'_, I> {
+        MeshDataMut {
+            vec: &mut self.indices,
+            dirty: &mut self.indices_dirty,
+            whole_vec: false,
+        }
+    }
 
     /// Just be careful
     pub fn vao_mut(&mut self) -> &mut VertexArray {
         &mut self.vao
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_mut<'a>(vec: &'a mut Vec<i32>, dirty: &'a mut DirtyRange) -> MeshDataMut<'a, i32> {
+        MeshDataMut {
+            vec,
+            dirty,
+            whole_vec: false,
+        }
+    }
+
+    #[test]
+    fn dirty_ranges_merge() {
+        let mut dirty = DirtyRange::default();
+        assert_eq!(dirty.take(), None);
+
+        dirty.mark(4..6);
+        dirty.mark(1..2);
+        dirty.mark(3..3);
+        assert_eq!(dirty.take(), Some(1..6));
+        assert_eq!(dirty.take(), None);
+    }
+
+    #[test]
+    fn edits_mark_what_changed() {
+        let mut vec = vec![0; 8];
+        let mut dirty = DirtyRange::default();
+
+        {
+            let mut data = data_mut(&mut vec, &mut dirty);
+            *data.get_mut(5).unwrap() = 1;
+            assert!(data.get_mut(8).is_none());
+            data.range_mut(2..4)[0] = 2;
+        }
+        assert_eq!(dirty.take(), Some(2..6));
+
+        {
+            let mut data = data_mut(&mut vec, &mut dirty);
+            data.push(3);
+            data.extend(vec![4, 5]);
+        }
+        assert_eq!(dirty.take(), Some(8..11));
+
+        {
+            let mut data = data_mut(&mut vec, &mut dirty);
+            data.pop();
+            data.truncate(4);
+        }
+        assert_eq!(dirty.take(), None);
+
+        {
+            let mut data = data_mut(&mut vec, &mut dirty);
+            data.as_mut_vec().push(6);
+        }
+        assert_eq!(dirty.take(), Some(0..5));
+        assert_eq!(vec, vec![0, 0, 2, 0, 6]);
+    }
+
+    #[test]
+    fn upload_plan() {
+        assert_eq!(Upload::plan(8, 8, None), Upload::Nothing);
+        assert_eq!(Upload::plan(8, 6, Some(2..4)), Upload::Range(2..4));
+        // grown past the buffer
+        assert_eq!(Upload::plan(8, 9, None), Upload::All);
+        assert_eq!(Upload::plan(8, 9, Some(8..9)), Upload::All);
+        // truncated after being marked
+        assert_eq!(Upload::plan(8, 3, Some(2..6)), Upload::Range(2..3));
+        assert_eq!(Upload::plan(8, 2, Some(2..6)), Upload::Nothing);
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    mem,
+    ptr,
+};
+
+use nalgebra_glm as glm;
+
+use super::{
+    BufferUsage,
+    Index,
+    Mesh,
+    Primitive,
+    Vertex,
+};
+
+//
+
+/// A vertex read from a Wavefront OBJ file.
+/// Vertex types that implement `From<ObjVertex>` can be loaded with `Mesh::from_obj()`.
+#[derive(Copy, Clone, Debug)]
+pub struct ObjVertex {
+    pub position: glm::Vec3,
+    /// Flipped so 0 is the top of the texture, like the rest of maru.
+    pub uv: glm::Vec2,
+    pub normal: glm::Vec3,
+}
+
+fn parse_floats<'a, I: Iterator<Item = &'a str>>(iter: I, line_num: usize) -> Result<Vec<f32>, String> {
+    iter.map(| s | {
+            s.parse::<f32>()
+             .map_err(| _ | format!("obj line {}: invalid number: {}", line_num, s))
+        })
+        .collect()
+}
+
+/// Resolves a 1-based or negative OBJ index.
+fn resolve_obj_index(s: &str, len: usize, line_num: usize) -> Result<usize, String> {
+    let idx: i64 = s.parse()
+                    .map_err(| _ | format!("obj line {}: invalid index: {}", line_num, s))?;
+    let resolved = if idx < 0 {
+        len as i64 + idx
+    } else {
+        idx - 1
+    };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(format!("obj line {}: index out of range: {}", line_num, s));
+    }
+    Ok(resolved as usize)
+}
+
+/// Reads the vertices and triangle indices of an OBJ file.
+fn parse_obj<T: From<ObjVertex>>(text: &str) -> Result<(Vec<T>, Vec<u32>), String> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut lookup: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_num = i + 1;
+        let line = match line.find('#') {
+            Some(at) => &line[..at],
+            None => line,
+        };
+
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let v = parse_floats(words, line_num)?;
+                if v.len() < 3 {
+                    return Err(format!("obj line {}: position needs 3 components", line_num));
+                }
+                positions.push(glm::vec3(v[0], v[1], v[2]));
+            }
+            Some("vt") => {
+                let v = parse_floats(words, line_num)?;
+                if v.len() < 2 {
+                    return Err(format!("obj line {}: uv needs 2 components", line_num));
+                }
+                uvs.push(glm::vec2(v[0], 1. - v[1]));
+            }
+            Some("vn") => {
+                let v = parse_floats(words, line_num)?;
+                if v.len() < 3 {
+                    return Err(format!("obj line {}: normal needs 3 components", line_num));
+                }
+                normals.push(glm::vec3(v[0], v[1], v[2]));
+            }
+            Some("f") => {
+                let mut face = Vec::with_capacity(4);
+                for word in words {
+                    let mut parts = word.split('/');
+
+                    let p = resolve_obj_index(parts.next().unwrap_or(""), positions.len(), line_num)?;
+                    let t = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve_obj_index(s, uvs.len(), line_num)?),
+                        _ => None,
+                    };
+                    let n = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve_obj_index(s, normals.len(), line_num)?),
+                        _ => None,
+                    };
+
+                    let idx = *lookup.entry((p, t, n)).or_insert_with(|| {
+                        vertices.push(T::from(ObjVertex {
+                            position: positions[p],
+                            uv: t.map(| t | uvs[t]).unwrap_or_else(glm::Vec2::zeros),
+                            normal: n.map(| n | normals[n]).unwrap_or_else(glm::Vec3::zeros),
+                        }));
+                        (vertices.len() - 1) as u32
+                    });
+                    face.push(idx);
+                }
+
+                if face.len() < 3 {
+                    return Err(format!("obj line {}: face needs at least 3 vertices", line_num));
+                }
+
+                // fan triangulation
+                for i in 1..(face.len() - 1) {
+                    indices.push(face[0]);
+                    indices.push(face[i]);
+                    indices.push(face[i + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((vertices, indices))
+}
+
+impl<T: Vertex + From<ObjVertex>> Mesh<T> {
+    /// Creates a mesh from the text of a Wavefront OBJ file.
+    /// Reads positions, uvs, normals and faces, faces with more than 3 vertices are triangulated.
+    /// Everything else is ignored.
+    pub fn from_obj(text: &str, usage: BufferUsage) -> Result<Self, String> {
+        let (vertices, indices) = parse_obj(text)?;
+        Self::new(vertices, indices, usage, Primitive::Triangles)
+    }
+}
+
+//
+
+// binary mesh format
+//   all numbers are little endian u32s
+//   header:
+//     magic b"MMSH"
+//     version
+//     size of a vertex in bytes
+//     vertex count
+//     size of an index in bytes
+//     index count
+//     primitive
+//   vertex data
+//   index data
+
+const MESH_MAGIC: &[u8; 4] = b"MMSH";
+const MESH_VERSION: u32 = 1;
+const MESH_HEADER_LEN: usize = 4 + 6 * 4;
+
+fn primitive_to_u32(primitive: Primitive) -> u32 {
+    match primitive {
+        Primitive::Points        => 0,
+        Primitive::Lines         => 1,
+        Primitive::LineStrip     => 2,
+        Primitive::LineLoop      => 3,
+        Primitive::Triangles     => 4,
+        Primitive::TriangleStrip => 5,
+        Primitive::TriangleFan   => 6,
+    }
+}
+
+fn primitive_from_u32(num: u32) -> Option<Primitive> {
+    match num {
+        0 => Some(Primitive::Points),
+        1 => Some(Primitive::Lines),
+        2 => Some(Primitive::LineStrip),
+        3 => Some(Primitive::LineLoop),
+        4 => Some(Primitive::Triangles),
+        5 => Some(Primitive::TriangleStrip),
+        6 => Some(Primitive::TriangleFan),
+        _ => None,
+    }
+}
+
+/// # Safety
+///
+/// `E` must have no padding bytes.
+unsafe fn slice_as_bytes<E: Copy>(slice: &[E]) -> &[u8] {
+    std::slice::from_raw_parts(slice.as_ptr() as *const u8, mem::size_of_val(slice))
+}
+
+/// # Safety
+///
+/// `bytes` must hold at least `count` `E`s, and any bit pattern must be a valid `E`.
+unsafe fn vec_from_bytes<E: Copy>(bytes: &[u8], count: usize) -> Vec<E> {
+    debug_assert!(bytes.len() >= count * mem::size_of::<E>());
+    let mut ret = Vec::<E>::with_capacity(count);
+    ptr::copy_nonoverlapping(bytes.as_ptr(), ret.as_mut_ptr() as *mut u8, count * mem::size_of::<E>());
+    ret.set_len(count);
+    ret
+}
+
+/// # Safety
+///
+/// See `Mesh::to_bytes()`.
+unsafe fn write_mesh<T: Copy, I: Copy>(vertices: &[T], indices: &[I], primitive: Primitive) -> Vec<u8> {
+    let vertex_bytes = slice_as_bytes(vertices);
+    let index_bytes = slice_as_bytes(indices);
+
+    let mut ret = Vec::with_capacity(MESH_HEADER_LEN + vertex_bytes.len() + index_bytes.len());
+    ret.extend_from_slice(MESH_MAGIC);
+    for num in &[
+        MESH_VERSION,
+        mem::size_of::<T>() as u32,
+        vertices.len() as u32,
+        mem::size_of::<I>() as u32,
+        indices.len() as u32,
+        primitive_to_u32(primitive),
+    ] {
+        ret.extend_from_slice(&num.to_le_bytes());
+    }
+    ret.extend_from_slice(vertex_bytes);
+    ret.extend_from_slice(index_bytes);
+    ret
+}
+
+/// # Safety
+///
+/// See `Mesh::from_bytes()`.
+unsafe fn read_mesh<T: Copy, I: Copy>(bytes: &[u8]) -> Result<(Vec<T>, Vec<I>, Primitive), String> {
+    if bytes.len() < MESH_HEADER_LEN || &bytes[0..4] != MESH_MAGIC {
+        return Err(String::from("not a maru mesh"));
+    }
+
+    let read_u32 = | at: usize | {
+        let start = 4 + at * 4;
+        u32::from_le_bytes(bytes[start..(start + 4)].try_into().unwrap())
+    };
+
+    let version = read_u32(0);
+    let vertex_size = read_u32(1) as usize;
+    let vertex_count = read_u32(2) as usize;
+    let index_size = read_u32(3) as usize;
+    let index_count = read_u32(4) as usize;
+    let primitive = read_u32(5);
+
+    if version != MESH_VERSION {
+        return Err(format!("unsupported mesh version: {}", version));
+    }
+    if vertex_size != mem::size_of::<T>() {
+        return Err(format!("vertex size mismatch: expected {}, found {}",
+                           mem::size_of::<T>(), vertex_size));
+    }
+    if index_size != mem::size_of::<I>() {
+        return Err(format!("index size mismatch: expected {}, found {}",
+                           mem::size_of::<I>(), index_size));
+    }
+    let primitive = primitive_from_u32(primitive)
+                        .ok_or_else(|| format!("invalid primitive: {}", primitive))?;
+
+    let truncated = || String::from("mesh data is truncated");
+    let vertex_start = MESH_HEADER_LEN;
+    let index_start = vertex_count.checked_mul(vertex_size)
+                                  .and_then(| len | len.checked_add(vertex_start))
+                                  .ok_or_else(truncated)?;
+    let end = index_count.checked_mul(index_size)
+                         .and_then(| len | len.checked_add(index_start))
+                         .ok_or_else(truncated)?;
+    if bytes.len() < end {
+        return Err(truncated());
+    }
+
+    let vertices = vec_from_bytes(&bytes[vertex_start..index_start], vertex_count);
+    let indices = vec_from_bytes(&bytes[index_start..end], index_count);
+    Ok((vertices, indices, primitive))
+}
+
+/// Binary serialization.
+/// `T` and `I` are written and read as raw bytes,
+///   so files are only compatible between builds with the same vertex layout.
+impl<T: Vertex + Copy, I: Index> Mesh<T, I> {
+    /// # Safety
+    ///
+    /// `T` must have no padding bytes, reading them is undefined behavior.
+    /// Use `#[repr(C)]` vertices with fields that fill the struct exactly.
+    pub unsafe fn to_bytes(&self) -> Vec<u8> {
+        write_mesh(self.vertices(), self.indices(), self.primitive())
+    }
+
+    /// # Safety
+    ///
+    /// Every bit pattern must be a valid `T`,
+    ///   so `T` can't contain `bool`s, enums, references or other types with invalid values.
+    /// Structs of floats and integers are fine.
+    pub unsafe fn from_bytes(bytes: &[u8], usage: BufferUsage) -> Result<Self, String> {
+        let (vertices, indices, primitive) = read_mesh(bytes)?;
+        Self::new(vertices, indices, usage, primitive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "
+# a quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 1
+vn 0 0 1
+f 1/1/1 2/1/1 3/2/1 4/2/1
+";
+
+    #[test]
+    fn obj_fan_triangulates() {
+        let (vertices, indices) = parse_obj::<ObjVertex>(QUAD).unwrap();
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(vertices[2].position, glm::vec3(1., 1., 0.));
+        assert_eq!(vertices[2].uv, glm::vec2(1., 0.));
+        assert_eq!(vertices[0].normal, glm::vec3(0., 0., 1.));
+    }
+
+    #[test]
+    fn obj_shares_vertices_and_resolves_negative_indices() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf -3 -1 -2\n";
+        let (vertices, indices) = parse_obj::<ObjVertex>(text).unwrap();
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, vec![0, 1, 2, 1, 3, 2]);
+    }
+
+    #[test]
+    fn obj_errors() {
+        assert!(parse_obj::<ObjVertex>("v 0 0\n").is_err());
+        assert!(parse_obj::<ObjVertex>("v 0 0 x\n").is_err());
+        assert!(parse_obj::<ObjVertex>("v 0 0 0\nf 1 2 3\n").is_err());
+        assert!(parse_obj::<ObjVertex>("v 0 0 0\nv 1 0 0\nf 1 2\n").is_err());
+        assert!(parse_obj::<ObjVertex>("v 0 0 0\nf 0 1 1\n").is_err());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let vertices = vec![[0f32, 1.], [2., 3.], [4., 5.]];
+        let indices = vec![0u16, 1, 2, 2, 1, 0];
+        let bytes = unsafe { write_mesh(&vertices, &indices, Primitive::TriangleStrip) };
+        assert_eq!(bytes.len(), MESH_HEADER_LEN + 3 * 8 + 6 * 2);
+
+        let (v, i, primitive) = unsafe { read_mesh::<[f32; 2], u16>(&bytes) }.unwrap();
+        assert_eq!(v, vertices);
+        assert_eq!(i, indices);
+        assert_eq!(primitive, Primitive::TriangleStrip);
+    }
+
+    #[test]
+    fn bytes_truncated() {
+        let vertices = vec![[0f32, 1.], [2., 3.]];
+        let indices = vec![0u32, 1];
+        let bytes = unsafe { write_mesh(&vertices, &indices, Primitive::Lines) };
+
+        for len in 0..bytes.len() {
+            assert!(unsafe { read_mesh::<[f32; 2], u32>(&bytes[..len]) }.is_err(), "length {}", len);
+        }
+        assert!(unsafe { read_mesh::<[f32; 2], u32>(&bytes) }.is_ok());
+    }
+
+    #[test]
+    fn bytes_header_checks() {
+        let vertices = vec![[0f32, 1.]];
+        let indices = vec![0u32];
+        let bytes = unsafe { write_mesh(&vertices, &indices, Primitive::Points) };
+
+        assert!(unsafe { read_mesh::<[f32; 3], u32>(&bytes) }.is_err());
+        assert!(unsafe { read_mesh::<[f32; 2], u16>(&bytes) }.is_err());
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert!(unsafe { read_mesh::<[f32; 2], u32>(&bad) }.is_err());
+
+        // huge counts must not overflow or read out of bounds
+        let mut bad = bytes.clone();
+        bad[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(unsafe { read_mesh::<[f32; 2], u32>(&bad) }.is_err());
+    }
+}
@@ -42,6 +42,9 @@ on drop, will buffer the data to the buffer, just based on how much of the slice
 
 //
 
+mod support;
+pub use support::*;
+
 mod shader;
 pub use shader::*;
 
@@ -60,18 +63,40 @@ pub use uniform::*;
 mod buffer;
 pub use buffer::*;
 
+mod fence;
+pub use fence::*;
+
+mod ring_buffer;
+pub use ring_buffer::*;
+
 mod vertex_array;
 pub use vertex_array::*;
+pub use maru_derive::Vertex;
 
 mod mesh;
 pub use mesh::*;
 
+mod mesh_io;
+pub use mesh_io::*;
+
 mod instancer;
 pub use instancer::*;
 
 mod canvas;
 pub use canvas::*;
 
+mod render_state;
+pub use render_state::*;
+
+mod clear;
+pub use clear::*;
+
+mod stats;
+pub use stats::*;
+
+mod profiler;
+pub use profiler::*;
+
 //
 
 use crate::math::AABB;
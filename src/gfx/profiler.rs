@@ -0,0 +1,243 @@
+use std::fmt;
+
+use gl::{
+    self,
+    types::*,
+};
+
+use crate::timer::{
+    RollingAverage,
+    Timer,
+};
+
+//
+
+/// Simple wrapper around an OpenGL query object.
+pub struct Query {
+    query: GLuint,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        let mut query = 0;
+        unsafe {
+            gl::GenQueries(1, &mut query);
+        }
+        Self {
+            query,
+        }
+    }
+
+    pub fn begin(&self, target: GLenum) {
+        unsafe {
+            gl::BeginQuery(target, self.query);
+        }
+    }
+
+    pub fn end(&self, target: GLenum) {
+        unsafe {
+            gl::EndQuery(target);
+        }
+    }
+
+    /// Checks if the result can be read without stalling.
+    pub fn is_available(&self) -> bool {
+        let mut available = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        available != 0
+    }
+
+    /// note: will stall if the result is not available
+    pub fn result(&self) -> u64 {
+        let mut result = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(self.query, gl::QUERY_RESULT, &mut result);
+        }
+        result
+    }
+
+    pub fn gl(&self) -> GLuint {
+        self.query
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(1, &self.query);
+        }
+    }
+}
+
+//
+
+struct Label {
+    name: String,
+    average: RollingAverage,
+}
+
+/// Queries issued in one frame.
+struct FrameQueries {
+    // (label index, query)
+    queries: Vec<(usize, Query)>,
+    used: usize,
+}
+
+/// GPU profiler using `GL_TIME_ELAPSED` queries.
+///
+/// Time is measured in labeled scopes, scopes can not be nested.
+/// Queries are kept for `frame_latency` frames before being read,
+///   so reading results does not stall the pipeline.
+/// If a result still isn't ready by then it is dropped.
+pub struct GpuProfiler {
+    frames: Vec<FrameQueries>,
+    frame: usize,
+    labels: Vec<Label>,
+    window: usize,
+    active: bool,
+}
+
+impl GpuProfiler {
+    /// `frame_latency` of 2 or 3 is usually enough to not stall,
+    ///   it is raised to 2 if lower, with 1 results would be read the frame after they're issued
+    ///   and almost never be ready.
+    /// `window` is the number of samples averaged per label.
+    pub fn new(frame_latency: usize, window: usize) -> Self {
+        let frame_latency = frame_latency.max(2);
+        let frames = (0..frame_latency).map(| _ | FrameQueries {
+                queries: Vec::new(),
+                used: 0,
+            })
+            .collect();
+
+        Self {
+            frames,
+            frame: 0,
+            labels: Vec::new(),
+            window,
+            active: false,
+        }
+    }
+
+    /// Should be called once at the start of every frame.
+    /// Collects the results of the oldest frame.
+    pub fn begin_frame(&mut self) {
+        assert!(!self.active, "gpu profiler scope still active at start of frame");
+
+        self.frame = (self.frame + 1) % self.frames.len();
+
+        let frame = &mut self.frames[self.frame];
+        for (label, query) in frame.queries[..frame.used].iter() {
+            if query.is_available() {
+                let secs = query.result() as f64 / 1_000_000_000.;
+                self.labels[*label].average.push(secs);
+            }
+        }
+        frame.used = 0;
+    }
+
+    fn label_index(&mut self, name: &str) -> usize {
+        match self.labels.iter().position(| l | l.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.labels.push(Label {
+                    name: String::from(name),
+                    average: RollingAverage::new(self.window),
+                });
+                self.labels.len() - 1
+            }
+        }
+    }
+
+    /// Starts timing a labeled scope.
+    /// Will panic if a scope is already active.
+    pub fn begin(&mut self, label: &str) {
+        assert!(!self.active, "gpu profiler scopes can not be nested");
+
+        let label = self.label_index(label);
+        let frame = &mut self.frames[self.frame];
+        if frame.used == frame.queries.len() {
+            frame.queries.push((label, Query::new()));
+        } else {
+            frame.queries[frame.used].0 = label;
+        }
+        frame.queries[frame.used].1.begin(gl::TIME_ELAPSED);
+        frame.used += 1;
+        self.active = true;
+    }
+
+    /// Ends the current scope.
+    pub fn end(&mut self) {
+        if self.active {
+            let frame = &self.frames[self.frame];
+            frame.queries[frame.used - 1].1.end(gl::TIME_ELAPSED);
+            self.active = false;
+        }
+    }
+
+    /// Times everything until the returned scope is dropped.
+    pub fn scope(&mut self, label: &str) -> GpuScope<'_> {
+        self.begin(label);
+        GpuScope {
+            profiler: self,
+        }
+    }
+
+    /// Rolling average of a label in seconds.
+    /// note: a label may be timed more than once per frame, each scope is a sample
+    pub fn average(&self, label: &str) -> Option<f64> {
+        self.labels.iter()
+            .find(| l | l.name == label)
+            .map(| l | l.average.average())
+    }
+
+    /// Rolling averages of every label alongside the CPU frame time.
+    pub fn report(&self, timer: &Timer) -> ProfileReport {
+        ProfileReport {
+            cpu_delta_time: timer.delta_time(),
+            cpu_average: timer.average_delta_time(),
+            gpu: self.labels.iter()
+                     .map(| l | (l.name.clone(), l.average.average()))
+                     .collect(),
+        }
+    }
+}
+
+/// A labeled scope being timed by a `GpuProfiler`.
+pub struct GpuScope<'a> {
+    profiler: &'a mut GpuProfiler,
+}
+
+impl<'a> Drop for GpuScope<'a> {
+    fn drop(&mut self) {
+        self.profiler.end();
+    }
+}
+
+/// Times in seconds.
+#[derive(Clone, Debug)]
+pub struct ProfileReport {
+    pub cpu_delta_time: f64,
+    pub cpu_average: f64,
+    pub gpu: Vec<(String, f64)>,
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cpu: {:.2}ms (avg {:.2}ms)",
+               self.cpu_delta_time * 1000.,
+               self.cpu_average * 1000.)?;
+        for (label, secs) in self.gpu.iter() {
+            write!(f, "\n{}: {:.2}ms", label, secs * 1000.)?;
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,22 @@
+use std::cell::Cell;
+
 use gl::{
     self,
     types::*,
 };
 
-use super::Shader;
+use super::{
+    Shader,
+    FrameStats,
+};
 
 //
 
+thread_local! {
+    /// Last program bound through `Program::bind()`.
+    static BOUND: Cell<GLuint> = const { Cell::new(0) };
+}
+
 /// Simple wrapper around an OpenGL program.
 #[derive(Debug)]
 pub struct Program {
@@ -52,6 +62,9 @@ impl Program {
         unsafe {
             gl::UseProgram(self.program);
         }
+        if BOUND.with(| bound | bound.replace(self.program)) != self.program {
+            FrameStats::record(| s | s.program_switches += 1);
+        }
     }
 
     pub fn gl(&self) -> GLuint {
@@ -61,6 +74,12 @@ impl Program {
 
 impl Drop for Program {
     fn drop(&mut self) {
+        // the name may be reused by a new program
+        BOUND.with(| bound | {
+            if bound.get() == self.program {
+                bound.set(0);
+            }
+        });
         unsafe {
             gl::DeleteProgram(self.program);
         }
@@ -0,0 +1,362 @@
+use gl::{
+    self,
+    types::*,
+};
+
+//
+
+/// Blend mode presets.
+/// Colors are assumed to be straight alpha except for `Premultiplied`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Disabled,
+    Alpha,
+    Premultiplied,
+    Additive,
+    /// Multiplies the destination by the source color, alpha is ignored,
+    ///   so fade to white rather than to transparent.
+    Multiply,
+    Screen,
+}
+
+impl BlendMode {
+    /// (src, dst) blend factors, `None` if blending is disabled.
+    pub fn factors(self) -> Option<(GLenum, GLenum)> {
+        match self {
+            BlendMode::Disabled      => None,
+            BlendMode::Alpha         => Some((gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Premultiplied => Some((gl::ONE, gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Additive      => Some((gl::SRC_ALPHA, gl::ONE)),
+            BlendMode::Multiply      => Some((gl::DST_COLOR, gl::ZERO)),
+            BlendMode::Screen        => Some((gl::ONE, gl::ONE_MINUS_SRC_COLOR)),
+        }
+    }
+}
+
+/// Comparison function for depth and stencil tests.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl From<CompareFunc> for GLenum {
+    fn from(func: CompareFunc) -> Self {
+        match func {
+            CompareFunc::Never        => gl::NEVER,
+            CompareFunc::Less         => gl::LESS,
+            CompareFunc::Equal        => gl::EQUAL,
+            CompareFunc::LessEqual    => gl::LEQUAL,
+            CompareFunc::Greater      => gl::GREATER,
+            CompareFunc::NotEqual     => gl::NOTEQUAL,
+            CompareFunc::GreaterEqual => gl::GEQUAL,
+            CompareFunc::Always       => gl::ALWAYS,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CullFace {
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl From<CullFace> for GLenum {
+    fn from(face: CullFace) -> Self {
+        match face {
+            CullFace::Front        => gl::FRONT,
+            CullFace::Back         => gl::BACK,
+            CullFace::FrontAndBack => gl::FRONT_AND_BACK,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    /// Clamps to the max value.
+    Increment,
+    IncrementWrap,
+    /// Clamps to 0.
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl From<StencilOp> for GLenum {
+    fn from(op: StencilOp) -> Self {
+        match op {
+            StencilOp::Keep          => gl::KEEP,
+            StencilOp::Zero          => gl::ZERO,
+            StencilOp::Replace       => gl::REPLACE,
+            StencilOp::Increment     => gl::INCR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::Decrement     => gl::DECR,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert        => gl::INVERT,
+        }
+    }
+}
+
+/// Stencil test and write settings, used for both front and back faces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StencilState {
+    /// Passes if `func(reference & read_mask, stencil & read_mask)` is true.
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    pub stencil_fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl StencilState {
+    /// Only tests, doesn't write.
+    pub fn test(func: CompareFunc, reference: i32) -> Self {
+        Self {
+            func,
+            reference,
+            read_mask: !0,
+            write_mask: 0,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+
+    /// Tests, and applies `op` to the stencil value where the test passes.
+    pub fn write(func: CompareFunc, reference: i32, op: StencilOp) -> Self {
+        Self {
+            write_mask: !0,
+            pass: op,
+            .. Self::test(func, reference)
+        }
+    }
+}
+
+/// Scissor rectangle in OpenGL window coordinates,
+///   origin is the bottom left of the framebuffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+//
+
+/// Fixed function state used while drawing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RenderState {
+    pub blend: BlendMode,
+    /// `None` disables the depth test.
+    pub depth_test: Option<CompareFunc>,
+    pub depth_write: bool,
+    /// `None` disables the scissor test.
+    pub scissor: Option<ScissorRect>,
+    /// `None` disables the stencil test.
+    pub stencil: Option<StencilState>,
+    /// `None` disables culling.
+    pub cull_face: Option<CullFace>,
+    /// r, g, b, a
+    pub color_mask: [bool; 4],
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            blend: BlendMode::Alpha,
+            depth_test: None,
+            depth_write: true,
+            scissor: None,
+            stencil: None,
+            cull_face: None,
+            color_mask: [true; 4],
+        }
+    }
+}
+
+fn set_enabled(cap: GLenum, enabled: bool) {
+    unsafe {
+        if enabled {
+            gl::Enable(cap);
+        } else {
+            gl::Disable(cap);
+        }
+    }
+}
+
+fn gl_bool(val: bool) -> GLboolean {
+    if val { gl::TRUE } else { gl::FALSE }
+}
+
+impl RenderState {
+    pub fn with_blend(self, blend: BlendMode) -> Self {
+        Self {
+            blend,
+            .. self
+        }
+    }
+
+    pub fn with_scissor(self, scissor: Option<ScissorRect>) -> Self {
+        Self {
+            scissor,
+            .. self
+        }
+    }
+
+    pub fn with_stencil(self, stencil: Option<StencilState>) -> Self {
+        Self {
+            stencil,
+            .. self
+        }
+    }
+
+    /// Sets all state, regardless of what is currently set.
+    pub fn apply(&self) {
+        Self::apply_blend(self.blend);
+        Self::apply_depth_test(self.depth_test);
+        Self::apply_depth_write(self.depth_write);
+        Self::apply_scissor(self.scissor);
+        Self::apply_stencil(self.stencil);
+        Self::apply_cull_face(self.cull_face);
+        Self::apply_color_mask(self.color_mask);
+    }
+
+    fn apply_blend(blend: BlendMode) {
+        match blend.factors() {
+            Some((src, dst)) => unsafe {
+                gl::Enable(gl::BLEND);
+                gl::BlendEquation(gl::FUNC_ADD);
+                gl::BlendFunc(src, dst);
+            },
+            None => set_enabled(gl::BLEND, false),
+        }
+    }
+
+    fn apply_depth_test(depth_test: Option<CompareFunc>) {
+        match depth_test {
+            Some(func) => unsafe {
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(func.into());
+            },
+            None => set_enabled(gl::DEPTH_TEST, false),
+        }
+    }
+
+    fn apply_depth_write(depth_write: bool) {
+        unsafe {
+            gl::DepthMask(gl_bool(depth_write));
+        }
+    }
+
+    fn apply_scissor(scissor: Option<ScissorRect>) {
+        match scissor {
+            Some(rect) => unsafe {
+                gl::Enable(gl::SCISSOR_TEST);
+                gl::Scissor(rect.x, rect.y, rect.width.max(0), rect.height.max(0));
+            },
+            None => set_enabled(gl::SCISSOR_TEST, false),
+        }
+    }
+
+    /// note: the stencil write mask is left fully enabled when the test is disabled,
+    ///         so clearing the stencil buffer works as expected
+    fn apply_stencil(stencil: Option<StencilState>) {
+        match stencil {
+            Some(st) => unsafe {
+                gl::Enable(gl::STENCIL_TEST);
+                gl::StencilFunc(st.func.into(), st.reference, st.read_mask);
+                gl::StencilMask(st.write_mask);
+                gl::StencilOp(st.stencil_fail.into(), st.depth_fail.into(), st.pass.into());
+            },
+            None => unsafe {
+                gl::Disable(gl::STENCIL_TEST);
+                gl::StencilMask(!0);
+            },
+        }
+    }
+
+    fn apply_cull_face(cull_face: Option<CullFace>) {
+        match cull_face {
+            Some(face) => unsafe {
+                gl::Enable(gl::CULL_FACE);
+                gl::CullFace(face.into());
+            },
+            None => set_enabled(gl::CULL_FACE, false),
+        }
+    }
+
+    fn apply_color_mask(mask: [bool; 4]) {
+        unsafe {
+            gl::ColorMask(gl_bool(mask[0]), gl_bool(mask[1]), gl_bool(mask[2]), gl_bool(mask[3]));
+        }
+    }
+}
+
+//
+
+/// Tracks the last applied `RenderState` and only sets what changed.
+/// note: if GL state is changed outside of the cache, call `invalidate()`
+#[derive(Clone, Debug, Default)]
+pub struct RenderStateCache {
+    current: Option<RenderState>,
+}
+
+impl RenderStateCache {
+    pub fn new() -> Self {
+        Self {
+            current: None,
+        }
+    }
+
+    pub fn apply(&mut self, state: &RenderState) {
+        match self.current {
+            None => state.apply(),
+            Some(cur) => {
+                if cur.blend != state.blend {
+                    RenderState::apply_blend(state.blend);
+                }
+                if cur.depth_test != state.depth_test {
+                    RenderState::apply_depth_test(state.depth_test);
+                }
+                if cur.depth_write != state.depth_write {
+                    RenderState::apply_depth_write(state.depth_write);
+                }
+                if cur.scissor != state.scissor {
+                    RenderState::apply_scissor(state.scissor);
+                }
+                if cur.stencil != state.stencil {
+                    RenderState::apply_stencil(state.stencil);
+                }
+                if cur.cull_face != state.cull_face {
+                    RenderState::apply_cull_face(state.cull_face);
+                }
+                if cur.color_mask != state.color_mask {
+                    RenderState::apply_color_mask(state.color_mask);
+                }
+            }
+        }
+        self.current = Some(*state);
+    }
+
+    /// Forces the next `apply()` to set everything.
+    pub fn invalidate(&mut self) {
+        self.current = None;
+    }
+
+    pub fn current(&self) -> Option<&RenderState> {
+        self.current.as_ref()
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    mem,
+    ptr,
+};
+
+use gl::{
+    self,
+    types::*,
+};
+
+use super::{
+    gl_supports,
+    Buffer,
+    Fence,
+    FrameStats,
+};
+
+//
+
+fn supports_buffer_storage() -> bool {
+    gl::BufferStorage::is_loaded() && gl_supports(4, 4, "GL_ARB_buffer_storage")
+}
+
+/// Streaming allocator over a `Buffer`.
+///
+/// The buffer is split into sections, each guarded by a `Fence`.
+/// Writes are handed out non-overlapping regions in order,
+///   when a section is left it is fenced,
+///   and when it is reached again the fence is waited on,
+///   so data the GPU is still reading is never overwritten.
+///
+/// Uses a persistently mapped buffer if the context supports `glBufferStorage`,
+///   GL 4.4 or `GL_ARB_buffer_storage`, otherwise maps each region unsynchronized.
+pub struct RingBuffer<T> {
+    buffer: Buffer<T>,
+    mapped: *mut T,
+    section_len: usize,
+    fences: Vec<Option<Fence>>,
+    section: usize,
+    head: usize,
+}
+
+impl<T> RingBuffer<T> {
+    /// Creates a new ring buffer of `section_count` sections, each `section_len` long.
+    pub fn new(section_len: usize, section_count: usize) -> Self {
+        assert!(section_len > 0 && section_count > 0);
+
+        let len = section_len * section_count;
+
+        let (buffer, mapped) = if supports_buffer_storage() {
+            let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+            let buffer = Buffer::storage(len, flags);
+            let mapped = buffer.map_range(0, len, flags);
+            (buffer, mapped)
+        } else {
+            (Buffer::empty(len, gl::STREAM_DRAW), ptr::null_mut())
+        };
+
+        let mut fences = Vec::with_capacity(section_count);
+        fences.resize_with(section_count, || None);
+
+        Self {
+            buffer,
+            mapped,
+            section_len,
+            fences,
+            section: 0,
+            head: 0,
+        }
+    }
+
+    /// Writes a slice into the buffer.
+    /// Returns the offset, in elements, the data was written to.
+    /// note: slice must not be longer than `section_len()`
+    pub fn push_slice(&mut self, data: &[T]) -> usize {
+        let offset = self.alloc(data.len());
+        if data.is_empty() {
+            return offset;
+        }
+
+        if self.mapped.is_null() {
+            let access = gl::MAP_WRITE_BIT |
+                         gl::MAP_UNSYNCHRONIZED_BIT |
+                         gl::MAP_INVALIDATE_RANGE_BIT;
+            let mapped = self.buffer.map_range(offset, data.len(), access);
+            if !mapped.is_null() {
+                unsafe {
+                    ptr::copy_nonoverlapping(data.as_ptr(), mapped, data.len());
+                }
+            }
+            self.buffer.unmap();
+        } else {
+            unsafe {
+                ptr::copy_nonoverlapping(data.as_ptr(), self.mapped.add(offset), data.len());
+            }
+        }
+        FrameStats::record_upload(mem::size_of_val(data));
+
+        offset
+    }
+
+    /// Reserves a region of `len` elements.
+    /// Returns the offset of the region.
+    pub fn alloc(&mut self, len: usize) -> usize {
+        assert!(len <= self.section_len, "ring buffer allocation larger than section");
+
+        let section_end = (self.section + 1) * self.section_len;
+        if self.head + len > section_end {
+            self.advance_section();
+        }
+
+        let ret = self.head;
+        self.head += len;
+        ret
+    }
+
+    fn advance_section(&mut self) {
+        self.fences[self.section] = Some(Fence::new());
+        self.section = (self.section + 1) % self.fences.len();
+        self.head = self.section * self.section_len;
+        // never hand out a region the GPU may still be reading
+        if let Some(fence) = self.fences[self.section].take() {
+            fence.wait_or_finish();
+        }
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        !self.mapped.is_null()
+    }
+
+    pub fn section_len(&self) -> usize {
+        self.section_len
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.section_len * self.fences.len()
+    }
+
+    pub fn buffer(&self) -> &Buffer<T> {
+        &self.buffer
+    }
+
+    pub fn gl(&self) -> GLuint {
+        self.buffer.gl()
+    }
+}
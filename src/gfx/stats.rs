@@ -0,0 +1,73 @@
+use std::{
+    cell::Cell,
+    fmt,
+};
+
+//
+
+thread_local! {
+    static CURRENT: Cell<FrameStats> = Cell::new(FrameStats::default());
+}
+
+/// Counts of the GL work done in a frame.
+/// Collected automatically by the wrappers in `gfx`,
+///   GL calls made directly are not counted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    pub draw_calls: usize,
+    pub instances: usize,
+    pub vertices: usize,
+    pub bytes_uploaded: usize,
+    /// note: counts `Texture::bind()`, which texture uniforms go through
+    pub texture_binds: usize,
+    /// note: only counts `Program::bind()` calls that change the bound program
+    pub program_switches: usize,
+    /// note: only counts uniforms set through `Location::set()`,
+    ///         textures are counted in `texture_binds` instead
+    pub uniform_uploads: usize,
+}
+
+impl FrameStats {
+    /// Stats for the frame so far.
+    pub fn current() -> Self {
+        CURRENT.with(| stats | stats.get())
+    }
+
+    /// Starts a new frame.
+    /// Returns the stats of the frame that just ended.
+    pub fn reset_frame() -> Self {
+        CURRENT.with(| stats | stats.replace(Self::default()))
+    }
+
+    pub(crate) fn record<F: FnOnce(&mut Self)>(f: F) {
+        CURRENT.with(| stats | {
+            let mut temp = stats.get();
+            f(&mut temp);
+            stats.set(temp);
+        });
+    }
+
+    pub(crate) fn record_draw(vertices: usize, instances: usize) {
+        Self::record(| s | {
+            s.draw_calls += 1;
+            s.instances += instances;
+            s.vertices += vertices * instances;
+        });
+    }
+
+    pub(crate) fn record_upload(bytes: usize) {
+        Self::record(| s | s.bytes_uploaded += bytes);
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "draw calls: {}", self.draw_calls)?;
+        writeln!(f, "instances: {}", self.instances)?;
+        writeln!(f, "vertices: {}", self.vertices)?;
+        writeln!(f, "uploaded: {}b", self.bytes_uploaded)?;
+        writeln!(f, "texture binds: {}", self.texture_binds)?;
+        writeln!(f, "program switches: {}", self.program_switches)?;
+        write!(f, "uniforms: {}", self.uniform_uploads)
+    }
+}
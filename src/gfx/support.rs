@@ -0,0 +1,35 @@
+use std::ffi::CStr;
+
+use gl::{
+    self,
+    types::*,
+};
+
+//
+
+/// Whether the current context is at least version `major`.`minor`,
+///   or has `extension`.
+/// A loaded function pointer doesn't mean the driver supports it,
+///   check this before using anything newer than GL 3.3.
+pub fn gl_supports(major: GLint, minor: GLint, extension: &str) -> bool {
+    let (mut have_major, mut have_minor, mut count) = (0, 0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut have_major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut have_minor);
+    }
+    if (have_major, have_minor) >= (major, minor) {
+        return true;
+    }
+
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    }
+    (0..count.max(0) as GLuint).any(| i | {
+        let name = unsafe {
+            gl::GetStringi(gl::EXTENSIONS, i)
+        };
+        !name.is_null() && unsafe {
+            CStr::from_ptr(name as *const _)
+        }.to_bytes() == extension.as_bytes()
+    })
+}
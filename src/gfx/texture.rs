@@ -6,6 +6,8 @@ use image::{
     RgbaImage,
 };
 
+use super::FrameStats;
+
 //
 
 // TODO
@@ -115,6 +117,16 @@ impl Texture {
         (self.width, self.height)
     }
 
+    /// Binds to `target` on texture unit `unit`, `gl::TEXTURE0` and up.
+    /// Counted as a texture bind in `FrameStats`.
+    pub fn bind(&self, unit: GLenum, target: GLenum) {
+        unsafe {
+            gl::ActiveTexture(unit);
+            gl::BindTexture(target, self.texture);
+        }
+        FrameStats::record(| s | s.texture_binds += 1);
+    }
+
     pub fn gl(&self) -> GLuint {
         self.texture
     }
@@ -17,6 +17,11 @@ use super::{
 
 pub trait Uniform {
     fn uniform(&self, loc: &Location);
+
+    /// Whether setting this is counted in `FrameStats::uniform_uploads`.
+    fn counts_as_upload(&self) -> bool {
+        true
+    }
 }
 
 impl Uniform for f32 {
@@ -104,12 +109,14 @@ impl<'a> TextureData<'a> {
     }
 }
 
+/// Counted as a texture bind rather than a uniform upload.
 impl Uniform for TextureData<'_> {
     fn uniform(&self, loc: &Location) {
         (self.select - gl::TEXTURE0).uniform(loc);
-        unsafe {
-            gl::ActiveTexture(self.select);
-            gl::BindTexture(self.bind_to, self.texture.gl());
-        }
+        self.texture.bind(self.select, self.bind_to);
+    }
+
+    fn counts_as_upload(&self) -> bool {
+        false
     }
 }
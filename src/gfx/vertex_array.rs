@@ -1,34 +1,228 @@
+use std::cell::Cell;
+
 use gl::{
     self,
     types::*,
 };
+use nalgebra_glm as glm;
+
+use memoffset::offset_of;
+
+use crate::math::{
+    Color,
+    Transform2d,
+    AABB,
+};
+
+use super::gl_supports;
 
 //
 
+/// How the shader will see a vertex attribute.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AttributeKind {
+    /// Converted to float, `glVertexAttribPointer`.
+    /// The default for integer types.
+    Float,
+    /// Integer types normalized to [0, 1] or [-1, 1], `glVertexAttribPointer`.
+    Normalized,
+    /// Integer types kept as integers, `glVertexAttribIPointer`.
+    /// Use `int`, `uint`, `ivecN` or `uvecN` in the shader.
+    Integer,
+    /// Doubles, `glVertexAttribLPointer`, needs GL 4.1 or `GL_ARB_vertex_attrib_64bit`.
+    /// Use `double` or `dvecN` in the shader.
+    Double,
+}
+
 /// OpenGL Vertex Attribute type.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct VertexAttribute {
     pub size: GLint,
     pub ty: GLenum,
-    pub normalized: bool,
+    pub kind: AttributeKind,
     pub stride: usize,
     pub offset: usize,
     pub divisor: GLuint,
 }
 
+impl VertexAttribute {
+    /// Number of locations the attribute takes up,
+    ///   `dvec3` and `dvec4` take two.
+    pub fn locations(&self) -> GLuint {
+        if self.kind == AttributeKind::Double && self.size > 2 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+thread_local! {
+    static DOUBLE_SUPPORT: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// Whether the current context supports `AttributeKind::Double`.
+/// Only queried once, on the first call.
+fn supports_double_attributes() -> bool {
+    DOUBLE_SUPPORT.with(| support | {
+        support.get().unwrap_or_else(|| {
+            let supported = gl_supports(4, 1, "GL_ARB_vertex_attrib_64bit");
+            support.set(Some(supported));
+            supported
+        })
+    })
+}
+
+/// Size in bytes of an OpenGL type used in vertex attributes.
+pub fn gl_type_size(ty: GLenum) -> usize {
+    match ty {
+        gl::BYTE | gl::UNSIGNED_BYTE => 1,
+        gl::SHORT | gl::UNSIGNED_SHORT | gl::HALF_FLOAT => 2,
+        gl::INT | gl::UNSIGNED_INT | gl::FLOAT | gl::FIXED => 4,
+        gl::DOUBLE => 8,
+        _ => panic!("unknown attribute type: {}", ty),
+    }
+}
+
 //
 
 pub trait Vertex {
     /// A `Buffer<Self>` will be bound to `gl::ARRAY_BUFFER`.
     /// Use this function to set VertexAttributes in the vao.
-    fn set_attributes(vao: &mut VertexArray);
+    /// Errors if an attribute isn't supported by the context.
+    fn set_attributes(vao: &mut VertexArray) -> Result<(), String>;
+}
+
+/// Types that can be used as fields of a `#[derive(Vertex)]` struct.
+pub trait VertexAttributes {
+    /// Enables attributes starting at `location`.
+    /// `base` has the stride, offset, divisor and normalization to use.
+    /// Returns the next free location.
+    fn enable_attributes(vao: &mut VertexArray,
+                         location: GLuint,
+                         base: VertexAttribute
+    ) -> Result<GLuint, String>;
+}
+
+// float types ignore the kind of the base attribute
+// integer types are converted to floats unless the base attribute is normalized or integer
+macro_rules! impl_vertex_attributes {
+    ($t:ty, $size:expr, $ty:expr, float) => {
+        impl_vertex_attributes!($t, $size, $ty, | _ | AttributeKind::Float);
+    };
+    ($t:ty, $size:expr, $ty:expr, integer) => {
+        impl_vertex_attributes!($t, $size, $ty, | kind | {
+            match kind {
+                AttributeKind::Normalized | AttributeKind::Integer => kind,
+                _ => AttributeKind::Float,
+            }
+        });
+    };
+    ($t:ty, $size:expr, $ty:expr, double) => {
+        impl_vertex_attributes!($t, $size, $ty, | _ | AttributeKind::Double);
+    };
+    ($t:ty, $size:expr, $ty:expr, $kind:expr) => {
+        impl VertexAttributes for $t {
+            fn enable_attributes(vao: &mut VertexArray,
+                                 location: GLuint,
+                                 base: VertexAttribute
+            ) -> Result<GLuint, String> {
+                let kind: fn(AttributeKind) -> AttributeKind = $kind;
+                let attrib = VertexAttribute {
+                    size: $size,
+                    ty: $ty,
+                    kind: kind(base.kind),
+                    .. base
+                };
+                vao.enable_attribute(location, attrib)?;
+                Ok(location + attrib.locations())
+            }
+        }
+    };
+}
+
+macro_rules! impl_matrix_attributes {
+    ($t:ty, $columns:expr, $rows:expr, $ty:expr, $kind:expr) => {
+        impl VertexAttributes for $t {
+            fn enable_attributes(vao: &mut VertexArray,
+                                 location: GLuint,
+                                 base: VertexAttribute
+            ) -> Result<GLuint, String> {
+                vao.enable_matrix_attribute(location, $columns, VertexAttribute {
+                    size: $rows,
+                    ty: $ty,
+                    kind: $kind,
+                    .. base
+                })
+            }
+        }
+    };
+}
+
+impl_vertex_attributes!(f32, 1, gl::FLOAT, float);
+impl_vertex_attributes!(f64, 1, gl::DOUBLE, double);
+impl_vertex_attributes!(i32, 1, gl::INT, integer);
+impl_vertex_attributes!(u32, 1, gl::UNSIGNED_INT, integer);
+impl_vertex_attributes!(i16, 1, gl::SHORT, integer);
+impl_vertex_attributes!(u16, 1, gl::UNSIGNED_SHORT, integer);
+impl_vertex_attributes!(i8, 1, gl::BYTE, integer);
+impl_vertex_attributes!(u8, 1, gl::UNSIGNED_BYTE, integer);
+
+impl_vertex_attributes!(glm::Vec2, 2, gl::FLOAT, float);
+impl_vertex_attributes!(glm::Vec3, 3, gl::FLOAT, float);
+impl_vertex_attributes!(glm::Vec4, 4, gl::FLOAT, float);
+impl_vertex_attributes!(glm::DVec2, 2, gl::DOUBLE, double);
+impl_vertex_attributes!(glm::DVec3, 3, gl::DOUBLE, double);
+impl_vertex_attributes!(glm::DVec4, 4, gl::DOUBLE, double);
+impl_vertex_attributes!(glm::IVec2, 2, gl::INT, integer);
+impl_vertex_attributes!(glm::IVec3, 3, gl::INT, integer);
+impl_vertex_attributes!(glm::IVec4, 4, gl::INT, integer);
+impl_vertex_attributes!(glm::UVec2, 2, gl::UNSIGNED_INT, integer);
+impl_vertex_attributes!(glm::UVec3, 3, gl::UNSIGNED_INT, integer);
+impl_vertex_attributes!(glm::UVec4, 4, gl::UNSIGNED_INT, integer);
+
+impl_vertex_attributes!([f32; 2], 2, gl::FLOAT, float);
+impl_vertex_attributes!([f32; 3], 3, gl::FLOAT, float);
+impl_vertex_attributes!([f32; 4], 4, gl::FLOAT, float);
+impl_vertex_attributes!([u8; 4], 4, gl::UNSIGNED_BYTE, integer);
+impl_vertex_attributes!([u16; 2], 2, gl::UNSIGNED_SHORT, integer);
+
+impl_vertex_attributes!(Color, 4, gl::FLOAT, float);
+impl_vertex_attributes!(AABB<f32>, 4, gl::FLOAT, float);
+impl_vertex_attributes!(AABB<i32>, 4, gl::INT, integer);
+
+// implemented here so math doesn't depend on gfx
+impl VertexAttributes for Transform2d {
+    fn enable_attributes(vao: &mut VertexArray,
+                         location: GLuint,
+                         base: VertexAttribute
+    ) -> Result<GLuint, String> {
+        let field = | offset: usize | VertexAttribute {
+            offset: base.offset + offset,
+            .. base
+        };
+        let at = glm::Vec2::enable_attributes(vao, location, field(offset_of!(Transform2d, position)))?;
+        let at = glm::Vec2::enable_attributes(vao, at, field(offset_of!(Transform2d, scale)))?;
+        f32::enable_attributes(vao, at, field(offset_of!(Transform2d, rotation)))
+    }
 }
 
+impl_matrix_attributes!(glm::Mat2, 2, 2, gl::FLOAT, AttributeKind::Float);
+impl_matrix_attributes!(glm::Mat3, 3, 3, gl::FLOAT, AttributeKind::Float);
+impl_matrix_attributes!(glm::Mat4, 4, 4, gl::FLOAT, AttributeKind::Float);
+impl_matrix_attributes!(glm::DMat2, 2, 2, gl::DOUBLE, AttributeKind::Double);
+impl_matrix_attributes!(glm::DMat3, 3, 3, gl::DOUBLE, AttributeKind::Double);
+impl_matrix_attributes!(glm::DMat4, 4, 4, gl::DOUBLE, AttributeKind::Double);
+
 //
 
 /// Simple wrapper around an OpenGL vertex array.
 pub struct VertexArray {
     vao: GLuint,
+    base_offset: usize,
+    /// Attributes are recorded here instead of sent to GL, for tests.
+    #[cfg(test)]
+    recorded: Option<Vec<(GLuint, VertexAttribute)>>,
 }
 
 impl VertexArray {
@@ -39,25 +233,101 @@ impl VertexArray {
             gl::GenVertexArrays(1, &mut vao);
         }
         Self {
-            vao
+            vao,
+            base_offset: 0,
+            #[cfg(test)]
+            recorded: None,
         }
     }
 
+    /// A vertex array that only records the attributes enabled on it, without a GL context.
+    #[cfg(test)]
+    pub(crate) fn recording() -> Self {
+        Self {
+            vao: 0,
+            base_offset: 0,
+            recorded: Some(Vec::new()),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn recorded(&self) -> &[(GLuint, VertexAttribute)] {
+        self.recorded.as_deref().unwrap_or(&[])
+    }
+
+    /// Sets an offset in bytes that will be added to the offset of every attribute
+    ///   enabled after this call.
+    /// Used to point attributes at a region of a streaming buffer.
+    pub fn set_base_offset(&mut self, offset: usize) {
+        self.base_offset = offset;
+    }
+
     /// Adds and enables a vertex attribute by number.
-    pub fn enable_attribute(&mut self, num: GLuint, attrib: VertexAttribute) {
+    /// Errors if the attribute is `AttributeKind::Double` and the context doesn't support it.
+    pub fn enable_attribute(&mut self, num: GLuint, attrib: VertexAttribute) -> Result<(), String> {
+        #[cfg(test)]
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push((num, VertexAttribute {
+                offset: attrib.offset + self.base_offset,
+                .. attrib
+            }));
+            return Ok(());
+        }
+
+        if attrib.kind == AttributeKind::Double && !supports_double_attributes() {
+            return Err("double vertex attributes need GL 4.1 or GL_ARB_vertex_attrib_64bit".to_string());
+        }
+
         unsafe {
             // note: redundant
             gl::BindVertexArray(self.vao);
             gl::EnableVertexAttribArray(num);
-            gl::VertexAttribPointer(
-                num,
-                attrib.size,
-                attrib.ty,
-                if attrib.normalized { gl::TRUE } else { gl::FALSE },
-                attrib.stride as GLsizei,
-                attrib.offset as _);
+
+            let stride = attrib.stride as GLsizei;
+            let offset = (attrib.offset + self.base_offset) as _;
+            match attrib.kind {
+                AttributeKind::Float | AttributeKind::Normalized => {
+                    let normalized = attrib.kind == AttributeKind::Normalized;
+                    gl::VertexAttribPointer(
+                        num,
+                        attrib.size,
+                        attrib.ty,
+                        if normalized { gl::TRUE } else { gl::FALSE },
+                        stride,
+                        offset);
+                }
+                AttributeKind::Integer => {
+                    gl::VertexAttribIPointer(num, attrib.size, attrib.ty, stride, offset);
+                }
+                AttributeKind::Double => {
+                    gl::VertexAttribLPointer(num, attrib.size, attrib.ty, stride, offset);
+                }
+            }
+
             gl::VertexAttribDivisor(num, attrib.divisor);
         }
+
+        Ok(())
+    }
+
+    /// Adds and enables a matrix attribute with `columns` columns, starting at `num`.
+    /// Each column takes up as many locations as its vector would, two for `dmat3` and `dmat4`.
+    /// `attrib.size` is the number of rows, `attrib.offset` is the offset of the first column.
+    /// Returns the next free location.
+    pub fn enable_matrix_attribute(&mut self,
+                                   num: GLuint,
+                                   columns: usize,
+                                   attrib: VertexAttribute
+    ) -> Result<GLuint, String> {
+        let column_size = attrib.size as usize * gl_type_size(attrib.ty);
+        let column_locations = attrib.locations();
+        for i in 0..columns {
+            self.enable_attribute(num + i as GLuint * column_locations, VertexAttribute {
+                offset: attrib.offset + i * column_size,
+                .. attrib
+            })?;
+        }
+        Ok(num + columns as GLuint * column_locations)
     }
 
     /// Disables a vertex attribute by number.
@@ -88,6 +358,11 @@ impl VertexArray {
 
 impl Drop for VertexArray {
     fn drop(&mut self) {
+        #[cfg(test)]
+        if self.recorded.is_some() {
+            return;
+        }
+
         unsafe {
             gl::DeleteVertexArrays(1, &mut self.vao);
         }
@@ -1,3 +1,6 @@
+// allows `#[derive(Vertex)]` to be used within maru
+extern crate self as maru;
+
 pub mod defaults;
 pub mod gfx;
 pub mod math;
@@ -6,3 +9,6 @@ pub mod sdl;
 pub mod coordinates;
 pub mod timer;
 pub mod state_machine;
+
+#[doc(hidden)]
+pub use memoffset;
@@ -1,6 +1,3 @@
-use gl::{
-    self,
-};
 use nalgebra_glm as glm;
 use sdl2::{
     keyboard::Keycode,
@@ -15,7 +12,10 @@ use maru::{
         ContextSettings,
     },
     gfx::*,
-    math::ext::*,
+    math::{
+        ext::*,
+        Color,
+    },
     defaults::two_dimensional::{
         self,
         Spritebatch,
@@ -38,7 +38,7 @@ fn main() {
 
     let m3_screen = ortho_screen(glm::vec2(600, 400));
 
-    let draw = ShapeDrawer::new(50);
+    let mut draw = ShapeDrawer::new();
     let prog = two_dimensional::default_program(None, None).unwrap();
     let locs = Locations::new(&prog);
 
@@ -63,10 +63,7 @@ fn main() {
             }
         }
 
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-            gl::Disable(gl::DEPTH_TEST);
-        }
+        ctx.clear(&ClearOptions::color(Color::new_rgba(0., 0., 0., 0.)));
 
         let dt = tm.step() as f32;
         time += dt;
@@ -85,7 +82,9 @@ fn main() {
         prog.bind();
         locs.reset();
         locs.screen().set(&m3_screen);
-        draw.filled_rectangle(&locs, 20., 20., 30., 30.);
+        locs.diffuse().set(&TextureData::diffuse(draw.white_texture()));
+        draw.filled_rectangle(20., 20., 30., 30.);
+        draw.flush(&locs);
 
         ctx.window.gl_swap_window();
         tm.sleep_millis(30);
@@ -3,6 +3,8 @@ use nalgebra_glm as glm;
 //
 
 // TODO eq derives
+/// Can be nested in a `#[derive(Vertex)]` struct,
+///   takes up 3 locations: position, scale, rotation.
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct Transform2d {
@@ -10,6 +10,12 @@ use sdl2::{
     },
 };
 
+use crate::gfx::{
+    clear_framebuffer,
+    ClearOptions,
+    RenderState,
+};
+
 pub struct ContextSettings {
     pub ogl_version_major: u32,
     pub ogl_version_minor: u32,
@@ -51,6 +57,8 @@ impl Context {
         let gl_attr = video.gl_attr();
         gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
         gl_attr.set_context_version(3, 3);
+        // for masking in the 2d drawer
+        gl_attr.set_stencil_size(8);
 
         let mut builder = video.window(&settings.window_name,
                                        settings.window_width,
@@ -82,11 +90,8 @@ impl Context {
 
         unsafe {
             gl::Viewport(0, 0, settings.window_width as GLint, settings.window_height as GLint);
-            gl::Enable(gl::BLEND);
-            gl::BlendEquation(gl::FUNC_ADD);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-            gl::ClearColor(0., 0., 0., 0.);
         }
+        RenderState::default().apply();
 
         let events = sdl.event_pump().unwrap();
 
@@ -132,30 +137,13 @@ impl Context {
         }
     }
 
-    pub fn window_dimensions(&self) -> (u32, u32) {
-        (self.settings.window_width, self.settings.window_height)
+    /// Clears the window.
+    /// Does not change which framebuffer is bound.
+    pub fn clear(&self, options: &ClearOptions) {
+        clear_framebuffer(0, options);
     }
 
-    /* TODO where to put this
-    //    also fix the math
-    pub fn set_scissor(&self, x1: u32, y1: u32, x2: u32, y2: u32) {
-        // TODO what to do ?
-        /*
-        if y1 > self.settings.window_height ||
-            y2 > self.settings.window_height {
-            panic!("invalid window scissor: {} {} {} {}", x1, y1, x2, y2);
-        }
-        */
-        unsafe {
-            gl::Enable(gl::SCISSOR_TEST);
-            gl::Scissor(x1 as i32, (self.settings.window_height - y1) as i32, x2 as i32, (self.settings.window_height - y2) as i32);
-        }
-    }
-
-    pub fn clear_scissor(&self) {
-        unsafe {
-            gl::Disable(gl::SCISSOR_TEST);
-        }
+    pub fn window_dimensions(&self) -> (u32, u32) {
+        (self.settings.window_width, self.settings.window_height)
     }
-    */
 }
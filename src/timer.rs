@@ -1,4 +1,5 @@
 use std::{
+    collections::VecDeque,
     thread,
     time::{
         self,
@@ -9,29 +10,83 @@ use std::{
 // TODO
 //   fixed update timer
 //   .sleep() ?
-//   average fps/delta needs ring buffer?
 
 // timer should be stepped after initial load
 // timer.start()?
 
+/// Average of the last `window` samples.
+#[derive(Clone, Debug)]
+pub struct RollingAverage {
+    samples: VecDeque<f64>,
+    window: usize,
+    sum: f64,
+}
+
+impl RollingAverage {
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0);
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window,
+            sum: 0.,
+        }
+    }
+
+    pub fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.window {
+            if let Some(old) = self.samples.pop_front() {
+                self.sum -= old;
+            }
+        }
+        self.samples.push_back(sample);
+        self.sum += sample;
+    }
+
+    /// Returns 0. if there are no samples.
+    pub fn average(&self) -> f64 {
+        if self.samples.is_empty() {
+            0.
+        } else {
+            self.sum / self.samples.len() as f64
+        }
+    }
+
+    pub fn last(&self) -> Option<f64> {
+        self.samples.back().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.sum = 0.;
+    }
+}
+
+//
+
 pub struct Timer {
     last: Instant,
     time_dt: f64,
+    average_dt: RollingAverage,
 }
 
 impl Timer {
+    /// Number of steps averaged by `average_delta_time()`.
+    pub const AVERAGE_WINDOW: usize = 60;
+
     pub fn new() -> Self {
         let last = Instant::now();
         let time_dt = 0.;
         Self {
             last,
             time_dt,
+            average_dt: RollingAverage::new(Self::AVERAGE_WINDOW),
         }
     }
 
     pub fn step(&mut self) -> f64 {
         self.time_dt = self.last.elapsed().as_secs_f64();
         self.last = Instant::now();
+        self.average_dt.push(self.time_dt);
         self.time_dt
     }
 
@@ -40,8 +95,73 @@ impl Timer {
         self.time_dt
     }
 
+    /// Average delta time of the last `AVERAGE_WINDOW` steps.
+    #[inline]
+    pub fn average_delta_time(&self) -> f64 {
+        self.average_dt.average()
+    }
+
     #[inline]
     pub fn sleep_millis(&self, millis: u64) {
         thread::sleep(time::Duration::from_millis(millis));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_average_is_zero() {
+        let avg = RollingAverage::new(4);
+        assert_eq!(avg.average(), 0.);
+        assert_eq!(avg.last(), None);
+    }
+
+    #[test]
+    fn averages_partial_window() {
+        let mut avg = RollingAverage::new(4);
+        avg.push(1.);
+        avg.push(2.);
+        avg.push(6.);
+        assert_eq!(avg.average(), 3.);
+        assert_eq!(avg.last(), Some(6.));
+    }
+
+    #[test]
+    fn drops_oldest_when_full() {
+        let mut avg = RollingAverage::new(3);
+        for sample in &[100., 1., 2., 3.] {
+            avg.push(*sample);
+        }
+        assert_eq!(avg.average(), 2.);
+
+        avg.push(9.);
+        assert_eq!(avg.average(), (2. + 3. + 9.) / 3.);
+    }
+
+    #[test]
+    fn window_of_one() {
+        let mut avg = RollingAverage::new(1);
+        avg.push(5.);
+        avg.push(7.);
+        assert_eq!(avg.average(), 7.);
+    }
+
+    #[test]
+    fn clear_resets() {
+        let mut avg = RollingAverage::new(2);
+        avg.push(5.);
+        avg.push(7.);
+        avg.clear();
+        assert_eq!(avg.average(), 0.);
+        avg.push(1.);
+        assert_eq!(avg.average(), 1.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_window_panics() {
+        RollingAverage::new(0);
+    }
+}